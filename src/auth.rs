@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! GitHub App authentication module
+//!
+//! Signs short-lived RS256 App JWTs from the configured private key and
+//! exchanges them for per-installation access tokens. Installation tokens are
+//! cached until shortly before they expire.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+use crate::config::Config;
+use crate::github::GitHubClient;
+
+/// Refresh installation tokens this long before GitHub expires them
+const REFRESH_MARGIN_SECS: i64 = 300;
+
+/// Cached installation access tokens keyed by installation ID
+static TOKEN_CACHE: LazyLock<Mutex<HashMap<u64, InstallationToken>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Login of the user rhodibot acts as, looked up once
+static BOT_LOGIN: OnceCell<String> = OnceCell::const_new();
+
+/// Installation access token with its expiry
+#[derive(Debug, Clone)]
+struct InstallationToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl InstallationToken {
+    fn is_fresh(&self) -> bool {
+        self.expires_at - Duration::seconds(REFRESH_MARGIN_SECS) > Utc::now()
+    }
+}
+
+/// GitHub App credentials
+pub struct AppAuth {
+    app_id: u64,
+    key: EncodingKey,
+}
+
+/// Claims of a GitHub App JWT
+#[derive(Debug, Serialize)]
+struct AppClaims {
+    iat: i64,
+    exp: i64,
+    iss: String,
+}
+
+impl AppAuth {
+    /// Build App credentials from configuration, if an App is configured
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        let (Some(app_id), Some(pem)) = (config.app_id, config.private_key.as_deref()) else {
+            return Ok(None);
+        };
+
+        let key = EncodingKey::from_rsa_pem(pem.as_bytes())
+            .context("Invalid GitHub App private key (expected RSA PEM)")?;
+
+        Ok(Some(Self { app_id, key }))
+    }
+
+    /// Sign an App JWT, valid for nine minutes
+    pub fn app_jwt(&self) -> Result<String> {
+        // Backdate issue time to tolerate clock drift with GitHub
+        let now = Utc::now().timestamp();
+        let claims = AppClaims {
+            iat: now - 60,
+            exp: now + 540,
+            iss: self.app_id.to_string(),
+        };
+
+        Ok(jsonwebtoken::encode(
+            &Header::new(Algorithm::RS256),
+            &claims,
+            &self.key,
+        )?)
+    }
+}
+
+/// Create a client authenticated as the App itself
+fn app_client(config: &Config) -> Result<GitHubClient> {
+    let auth = AppAuth::from_config(config)?
+        .context("GitHub App credentials are not configured")?;
+
+    Ok(GitHubClient::with_token(config, Some(auth.app_jwt()?)))
+}
+
+/// Get an access token for an installation, from cache or freshly issued
+pub async fn installation_token(config: &Config, installation_id: u64) -> Result<String> {
+    if let Some(cached) = TOKEN_CACHE
+        .lock()
+        .expect("token cache poisoned")
+        .get(&installation_id)
+        .filter(|t| t.is_fresh())
+    {
+        return Ok(cached.token.clone());
+    }
+
+    let app_client = app_client(config)?;
+    let issued = app_client.create_installation_token(installation_id).await?;
+
+    debug!(
+        "Issued token for installation {} (expires {})",
+        installation_id, issued.expires_at
+    );
+
+    TOKEN_CACHE.lock().expect("token cache poisoned").insert(
+        installation_id,
+        InstallationToken {
+            token: issued.token.clone(),
+            expires_at: issued.expires_at,
+        },
+    );
+
+    Ok(issued.token)
+}
+
+/// Look up the installation ID of the App on a repository, `None` if not installed
+pub async fn repo_installation_id(config: &Config, owner: &str, repo: &str) -> Result<Option<u64>> {
+    let app_client = app_client(config)?;
+
+    Ok(app_client
+        .get_repo_installation(owner, repo)
+        .await?
+        .map(|installation| installation.id))
+}
+
+/// Look up the installation ID of the App on an organization, `None` if not installed
+pub async fn org_installation_id(config: &Config, org: &str) -> Result<Option<u64>> {
+    let app_client = app_client(config)?;
+
+    Ok(app_client
+        .get_org_installation(org)
+        .await?
+        .map(|installation| installation.id))
+}
+
+/// Login of the user rhodibot comments and opens issues as
+///
+/// That is the App's bot user (`<slug>[bot]`) with App credentials, and
+/// otherwise the owner of `GITHUB_TOKEN`.
+pub async fn bot_login(config: &Config) -> Result<&'static str> {
+    let login = BOT_LOGIN
+        .get_or_try_init(|| async {
+            if AppAuth::from_config(config)?.is_some() {
+                let app = app_client(config)?.get_app().await?;
+                Ok::<_, anyhow::Error>(format!("{}[bot]", app.slug))
+            } else {
+                let user = GitHubClient::new(config).get_authenticated_user().await?;
+                Ok(user.login)
+            }
+        })
+        .await?;
+
+    Ok(login.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_refreshed_before_they_expire() {
+        let expiring_in = |minutes| InstallationToken {
+            token: "token".to_string(),
+            expires_at: Utc::now() + Duration::minutes(minutes),
+        };
+
+        assert!(expiring_in(60).is_fresh());
+        assert!(!expiring_in(4).is_fresh());
+        assert!(!expiring_in(-1).is_fresh());
+    }
+}
@@ -0,0 +1,282 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! Compliance badges
+//!
+//! A badge shows the policy pack and score of a repository's default branch,
+//! colored by the check-run conclusion of that report. Badges are cached per
+//! repository, so a README embedding one does not check the repository on
+//! every page view; a push to the default branch refreshes the cached badge.
+//! Only public repositories the App is installed on have badges.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::config::Config;
+use crate::github::GitHubClient;
+use crate::rsr::{self, ComplianceReport, report_conclusion};
+use crate::source::GitHubSource;
+
+/// Cached badges beyond which expired, then the oldest, entries are dropped
+const MAX_CACHED: usize = 10_000;
+
+/// Longest a badge is cached for a repository that could not be checked
+const FAILURE_TTL: Duration = Duration::from_secs(30);
+
+/// Badges by lowercased `owner/repo`
+static CACHE: LazyLock<Mutex<HashMap<String, Cached>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+struct Cached {
+    /// `None` for a repository without a badge
+    badge: Option<Badge>,
+    ttl: Duration,
+    expires: Instant,
+}
+
+/// What a badge shows
+#[derive(Debug, Clone)]
+pub struct Badge {
+    pub label: String,
+    pub message: String,
+    /// shields.io color name
+    pub color: &'static str,
+}
+
+/// shields.io endpoint badge
+///
+/// See <https://shields.io/badges/endpoint-badge>.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShieldsBadge {
+    pub schema_version: u8,
+    pub label: String,
+    pub message: String,
+    pub color: &'static str,
+    pub cache_seconds: u64,
+}
+
+impl Badge {
+    /// Badge for a report, e.g. "RSR standard | 87%"
+    pub fn from_report(report: &ComplianceReport) -> Self {
+        let color = match report_conclusion(report) {
+            "success" => "brightgreen",
+            "neutral" => "yellow",
+            _ => "red",
+        };
+
+        Self {
+            label: format!("RSR {}", report.policy),
+            message: format!("{:.0}%", report.percentage),
+            color,
+        }
+    }
+
+    /// Badge for a repository that could not be checked
+    fn unknown() -> Self {
+        Self {
+            label: "RSR".to_string(),
+            message: "unknown".to_string(),
+            color: "lightgrey",
+        }
+    }
+
+    /// shields.io endpoint JSON for this badge
+    pub fn shields(&self, ttl: Duration) -> ShieldsBadge {
+        ShieldsBadge {
+            schema_version: 1,
+            label: self.label.clone(),
+            message: self.message.clone(),
+            color: self.color,
+            cache_seconds: ttl.as_secs(),
+        }
+    }
+
+    /// Flat SVG badge in the style of shields.io
+    pub fn svg(&self) -> String {
+        let label = escape(&self.label);
+        let message = escape(&self.message);
+        let label_width = text_width(&self.label) + 10;
+        let message_width = text_width(&self.message) + 10;
+        let width = label_width + message_width;
+        let label_x = label_width as f32 / 2.0;
+        let message_x = label_width as f32 + message_width as f32 / 2.0;
+        let color = hex_color(self.color);
+
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">
+<title>{label}: {message}</title>
+<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+<clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+<g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/><rect width="{width}" height="20" fill="url(#s)"/></g>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+<text x="{label_x}" y="15" fill="#010101" fill-opacity=".3">{label}</text><text x="{label_x}" y="14">{label}</text>
+<text x="{message_x}" y="15" fill="#010101" fill-opacity=".3">{message}</text><text x="{message_x}" y="14">{message}</text>
+</g>
+</svg>
+"##
+        )
+    }
+}
+
+/// Approximate width of text in 11px Verdana
+fn text_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match c {
+            'i' | 'j' | 'l' | '.' | ',' | ':' | ';' | '!' | '|' | '\'' | ' ' => 4,
+            'f' | 'r' | 't' | 'I' | '(' | ')' | '-' => 5,
+            'm' | 'w' | 'M' | 'W' | '%' => 11,
+            c if c.is_ascii_uppercase() || c.is_ascii_digit() => 8,
+            _ => 7,
+        })
+        .sum()
+}
+
+/// Hex value of a shields.io color name
+fn hex_color(color: &str) -> &'static str {
+    match color {
+        "brightgreen" => "#4c1",
+        "yellow" => "#dfb317",
+        "red" => "#e05d44",
+        _ => "#9f9f9f",
+    }
+}
+
+/// Escape text for SVG
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn key(owner: &str, repo: &str) -> String {
+    format!("{}/{}", owner, repo).to_lowercase()
+}
+
+/// Badge of a repository's default branch, checked if the cached one expired
+///
+/// Returned with how long it may be cached, or `None` if the repository is
+/// not public or the App is not installed on it.
+pub async fn get(config: &Config, owner: &str, repo: &str) -> Option<(Badge, Duration)> {
+    let key = key(owner, repo);
+
+    {
+        let cache = CACHE.lock().expect("badge cache poisoned");
+        if let Some(cached) = cache.get(&key)
+            && cached.expires > Instant::now()
+        {
+            return cached.badge.clone().map(|badge| (badge, cached.ttl));
+        }
+    }
+
+    let (badge, ttl) = match check(config, owner, repo).await {
+        Ok(report) => (report.as_ref().map(Badge::from_report), config.badge_ttl),
+        Err(e) => {
+            warn!("Failed to check {}/{} for its badge: {:#}", owner, repo, e);
+            (Some(Badge::unknown()), config.badge_ttl.min(FAILURE_TTL))
+        }
+    };
+
+    insert(key, badge.clone(), ttl);
+    badge.map(|badge| (badge, ttl))
+}
+
+/// Refresh the cached badge from a new default-branch report
+pub fn update(report: &ComplianceReport, ttl: Duration) {
+    insert(
+        key(&report.owner, &report.repo),
+        Some(Badge::from_report(report)),
+        ttl,
+    );
+}
+
+fn insert(key: String, badge: Option<Badge>, ttl: Duration) {
+    let now = Instant::now();
+    let mut cache = CACHE.lock().expect("badge cache poisoned");
+    if !cache.contains_key(&key) {
+        make_room(&mut cache, MAX_CACHED, now);
+    }
+    cache.insert(
+        key,
+        Cached {
+            badge,
+            ttl,
+            expires: now + ttl,
+        },
+    );
+}
+
+/// Drop expired entries, then those expiring first, to fit a new entry
+fn make_room(cache: &mut HashMap<String, Cached>, max: usize, now: Instant) {
+    if cache.len() < max {
+        return;
+    }
+
+    cache.retain(|_, cached| cached.expires > now);
+    while cache.len() >= max {
+        let Some(oldest) = cache
+            .iter()
+            .min_by_key(|(_, cached)| cached.expires)
+            .map(|(key, _)| key.clone())
+        else {
+            break;
+        };
+        cache.remove(&oldest);
+    }
+}
+
+/// Report of a repository's default branch
+///
+/// `None` if the repository is not public or the App is not installed on it.
+async fn check(
+    config: &Config,
+    owner: &str,
+    repo: &str,
+) -> anyhow::Result<Option<ComplianceReport>> {
+    let Some(client) = GitHubClient::for_installed_repo(config, owner, repo).await? else {
+        return Ok(None);
+    };
+    if client.get_repository(owner, repo).await?.private {
+        return Ok(None);
+    }
+
+    let source = GitHubSource::new(&client, owner, repo, None);
+    Ok(Some(rsr::check_compliance(&source).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_cache_drops_expired_then_oldest_entries() {
+        let now = Instant::now();
+        let entry = |expires| Cached {
+            badge: None,
+            ttl: Duration::ZERO,
+            expires,
+        };
+        let cases = [
+            (vec![("a", 1), ("b", 2)], 3, vec!["a", "b"]),
+            (vec![("a", 1), ("b", 2), ("c", 3)], 3, vec!["b", "c"]),
+            (vec![("a", 3), ("b", 1), ("c", 2)], 3, vec!["a", "c"]),
+            (vec![("a", 3), ("b", 0), ("c", 0)], 3, vec!["a"]),
+            (vec![("a", 3), ("b", 1), ("c", 2)], 1, vec![]),
+        ];
+
+        for (entries, max, kept) in cases {
+            let mut cache: HashMap<String, Cached> = entries
+                .iter()
+                .map(|(key, secs)| (key.to_string(), entry(now + Duration::from_secs(*secs))))
+                .collect();
+            make_room(&mut cache, max, now);
+
+            let mut keys: Vec<&str> = cache.keys().map(String::as_str).collect();
+            keys.sort();
+            assert_eq!(keys, kept, "{:?} within {}", entries, max);
+        }
+    }
+}
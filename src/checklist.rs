@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! The RSR checklist issue
+//!
+//! Each repository has at most one checklist issue, found by its label or the
+//! hidden marker in its body. After every default-branch report the issue is
+//! rewritten so its checkboxes match the report, closed once the repository
+//! is compliant and reopened if it regresses.
+
+use anyhow::Result;
+use tracing::info;
+
+use crate::github::{GitHubClient, Issue, UpdateIssue};
+use crate::policy;
+use crate::rsr::{ComplianceReport, RepoConfig, report_conclusion};
+use crate::templates;
+
+/// Title of the checklist issue
+pub const TITLE: &str = "[Rhodibot] RSR Compliance Checklist";
+
+/// Label identifying the checklist issue
+pub const LABEL: &str = "rsr-compliance";
+
+/// Hidden marker identifying the checklist issue if its label was removed
+pub const MARKER: &str = "<!-- rhodibot:rsr-checklist -->";
+
+/// Body of the checklist issue, starting with the marker
+pub fn body(
+    owner: &str,
+    repo: &str,
+    repo_config: &RepoConfig,
+    report: Option<&ComplianceReport>,
+) -> Result<String> {
+    let base_policy = repo_config
+        .custom_policy()
+        .map_or(repo_config.policy, |custom| custom.base());
+    let packs = policy::current();
+    let checklist = templates::checklist(
+        owner,
+        repo,
+        repo_config.policy,
+        packs.get(base_policy),
+        report,
+    )?;
+
+    Ok(format!("{}\n{}", MARKER, checklist))
+}
+
+/// Whether an issue is the checklist `bot`, the user rhodibot acts as, opened
+fn is_checklist(issue: &Issue, bot: &str) -> bool {
+    issue.user.login == bot
+        && (issue
+            .body
+            .as_deref()
+            .is_some_and(|body| body.contains(MARKER))
+            || issue.title == TITLE)
+}
+
+/// Find the checklist issue rhodibot opened earlier, open or closed
+///
+/// Issues opened by anyone but `bot` are never taken for it, whatever their
+/// title or body.
+pub async fn find(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    bot: &str,
+) -> Result<Option<Issue>> {
+    let is_checklist = |issue: &Issue| is_checklist(issue, bot);
+
+    let labelled = client.list_issues_with_label(owner, repo, LABEL).await?;
+    if let Some(issue) = labelled.into_iter().find(is_checklist) {
+        return Ok(Some(issue));
+    }
+
+    let searched = client
+        .search_issues(owner, repo, "\"rhodibot:rsr-checklist\" in:body")
+        .await?;
+
+    Ok(searched.into_iter().find(is_checklist))
+}
+
+/// Bring the checklist issue in line with the latest report
+///
+/// Without a report (e.g. an empty repository) the issue lists the checks of
+/// the pack. A compliant repository without an issue does not get one. `bot`
+/// is the user rhodibot acts as; see [`find`].
+pub async fn sync(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    bot: &str,
+    repo_config: &RepoConfig,
+    report: Option<&ComplianceReport>,
+) -> Result<Option<Issue>> {
+    let compliant = report.is_some_and(|report| report_conclusion(report) == "success");
+    let body = body(owner, repo, repo_config, report)?;
+
+    let Some(issue) = find(client, owner, repo, bot).await? else {
+        if compliant {
+            return Ok(None);
+        }
+
+        let issue = client
+            .create_issue(owner, repo, TITLE, &body, &["documentation", LABEL])
+            .await?;
+        info!("Created RSR checklist issue: {}", issue.html_url);
+        return Ok(Some(issue));
+    };
+
+    let number = issue.number;
+    let close = compliant && issue.state == "open";
+    let reopen = !compliant && issue.state == "closed";
+    let mut issue = issue;
+
+    if issue.body.as_deref() != Some(body.as_str()) {
+        let update = UpdateIssue {
+            body: Some(body),
+            ..Default::default()
+        };
+        issue = client
+            .update_issue(owner, repo, number, &update)
+            .await?;
+    }
+
+    if close {
+        issue = client.close_issue(owner, repo, number).await?;
+        info!("Closed RSR checklist issue: {}", issue.html_url);
+    } else if reopen {
+        issue = client.reopen_issue(owner, repo, number).await?;
+        info!("Reopened RSR checklist issue: {}", issue.html_url);
+    }
+
+    Ok(Some(issue))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::User;
+    use crate::rsr::tests::{check, report};
+    use crate::rsr::{CheckStatus, Severity};
+
+    #[test]
+    fn only_the_bots_issue_is_the_checklist() {
+        let marked = Some(format!("{}\n- [ ] SECURITY.md", MARKER));
+        let cases = [
+            ("rhodibot[bot]", TITLE, marked.clone(), true),
+            ("rhodibot[bot]", "Renamed", marked.clone(), true),
+            ("rhodibot[bot]", TITLE, None, true),
+            ("rhodibot[bot]", "Bug report", None, false),
+            ("mallory", TITLE, marked.clone(), false),
+            ("mallory", TITLE, None, false),
+        ];
+
+        for (login, title, body, expected) in cases {
+            let issue = Issue {
+                number: 1,
+                title: title.to_string(),
+                html_url: String::new(),
+                state: "open".to_string(),
+                body,
+                user: User {
+                    login: login.to_string(),
+                },
+                pull_request: None,
+            };
+            assert_eq!(
+                is_checklist(&issue, "rhodibot[bot]"),
+                expected,
+                "{:?}",
+                issue
+            );
+        }
+    }
+
+    #[test]
+    fn checkboxes_follow_the_report() {
+        let repo_config = RepoConfig::default();
+        let report = report(vec![
+            check("README.adoc", Severity::Required, CheckStatus::Pass),
+            check("SECURITY.md", Severity::Required, CheckStatus::Fail),
+            check("STATE.scm", Severity::Optional, CheckStatus::Skip),
+        ]);
+
+        let text = body("owner", "repo", &repo_config, Some(&report)).unwrap();
+        assert!(text.starts_with(MARKER), "{}", text);
+        assert!(text.contains("- [x] `README.adoc`"), "{}", text);
+        assert!(text.contains("- [ ] `SECURITY.md`"), "{}", text);
+        assert!(!text.contains("STATE.scm"), "{}", text);
+
+        // Without a report, every file of the pack is left to do
+        let text = body("owner", "repo", &repo_config, None).unwrap();
+        assert!(text.contains("- [ ] `README.adoc`"), "{}", text);
+        assert!(!text.contains("- [x]"), "{}", text);
+    }
+}
@@ -0,0 +1,467 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! Pluggable compliance checks
+//!
+//! Every check implements [`ComplianceCheck`] and lives in a
+//! [`CheckRegistry`] with the severity its policy pack gives it. The engine in
+//! [`crate::rsr`] iterates the registry and owns all scoring and status logic,
+//! so new checks only describe what they look for.
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::policy::{BanRule, FileRule, LicenseRule, PackDocument};
+use crate::rsr::{CheckCategory, Location, RepoConfig, Severity};
+use crate::source::{RepoSource, TreeEntry, TreeIndex};
+
+/// Points awarded for each extra required file listed in `.rsr.toml`
+const REPO_REQUIRE_POINTS: u8 = 3;
+
+/// A single compliance check
+#[async_trait]
+pub trait ComplianceCheck: Send + Sync {
+    /// Name of the check in reports, `skip` and `severity_overrides`
+    fn id(&self) -> String;
+
+    /// Short description of what the check looks for
+    fn description(&self) -> String;
+
+    fn category(&self) -> CheckCategory;
+
+    /// Points awarded when the check passes
+    fn points(&self) -> u8;
+
+    /// How to fix a failing check
+    fn remediation(&self) -> String;
+
+    /// Evaluate the check, `None` if there is nothing to report
+    ///
+    /// Fails if the repository cannot be read, rather than reporting what
+    /// could not be seen as missing.
+    async fn evaluate(&self, ctx: &CheckContext<'_>) -> Result<Option<Evaluation>>;
+}
+
+/// Everything a check can inspect
+pub struct CheckContext<'a> {
+    pub source: &'a dyn RepoSource,
+    /// Every path in the repository, fetched once per report
+    pub tree: TreeIndex,
+}
+
+/// Result of evaluating a check
+///
+/// The engine turns this into a status and score based on the check's
+/// effective severity.
+#[derive(Debug)]
+pub enum Evaluation {
+    /// The requirement is met
+    Pass(String),
+    /// Something required is absent
+    Missing(String),
+    /// The requirement is met in part, earning `points`, with what falls short
+    /// at `locations`
+    Partial {
+        points: u8,
+        message: String,
+        locations: Vec<Location>,
+    },
+    /// Something the policy forbids is present at `locations`
+    Violation {
+        message: String,
+        locations: Vec<Location>,
+    },
+}
+
+/// Ordered collection of checks evaluated for a report
+pub struct CheckRegistry {
+    /// Checks with their severity in the policy pack, before repo overrides
+    checks: Vec<(Box<dyn ComplianceCheck>, Severity)>,
+}
+
+impl CheckRegistry {
+    /// Registry of the checks defined by a policy pack
+    pub fn builtin(pack: &PackDocument) -> Self {
+        let mut registry = Self { checks: Vec::new() };
+
+        for file in &pack.files {
+            registry.register(Box::new(file.clone()), file.severity);
+        }
+        registry.register(Box::new(pack.license.clone()), pack.license.severity);
+        for banned in &pack.banned {
+            registry.register(Box::new(banned.clone()), banned.severity);
+        }
+
+        registry
+    }
+
+    /// Built-in checks plus the extra checks configured by a repo
+    ///
+    /// Adds the `require` and `ban` entries and, with `policy = "custom"`,
+    /// the checks of the custom pack.
+    pub fn for_repo(pack: &PackDocument, repo_config: &RepoConfig) -> Self {
+        let mut registry = Self::builtin(pack);
+
+        for path in &repo_config.require {
+            if registry.get(path).is_none() {
+                registry.register(
+                    Box::new(RepoRequiredFile {
+                        path: path.clone(),
+                        description: format!("{} (required by .rsr.toml)", path),
+                        points: REPO_REQUIRE_POINTS,
+                    }),
+                    Severity::Required,
+                );
+            }
+        }
+        for pattern in &repo_config.ban {
+            if registry.get(&format!("no-{}", pattern)).is_none() {
+                registry.register(
+                    Box::new(RepoBannedPattern {
+                        pattern: pattern.clone(),
+                        description: format!("{} (banned by .rsr.toml)", pattern),
+                    }),
+                    Severity::Required,
+                );
+            }
+        }
+
+        let custom_checks = repo_config
+            .custom_policy()
+            .map(|custom| custom.checks)
+            .unwrap_or_default();
+        for check in custom_checks {
+            if check.kind().is_none_or(|id| registry.get(&id).is_some()) {
+                continue;
+            }
+            match (check.require, check.ban) {
+                (Some(path), None) => registry.register(
+                    Box::new(RepoRequiredFile {
+                        description: check
+                            .description
+                            .unwrap_or_else(|| format!("{} (custom policy)", path)),
+                        path,
+                        points: check.points.unwrap_or(REPO_REQUIRE_POINTS),
+                    }),
+                    check.severity,
+                ),
+                (None, Some(pattern)) => registry.register(
+                    Box::new(RepoBannedPattern {
+                        description: check
+                            .description
+                            .unwrap_or_else(|| format!("{} (banned by custom policy)", pattern)),
+                        pattern,
+                    }),
+                    check.severity,
+                ),
+                _ => {}
+            }
+        }
+
+        registry
+    }
+
+    /// Add a check with its pack severity, evaluated after those already registered
+    pub fn register(&mut self, check: Box<dyn ComplianceCheck>, severity: Severity) {
+        self.checks.push((check, severity));
+    }
+
+    /// Look up a check and its pack severity by id
+    pub fn get(&self, id: &str) -> Option<(&dyn ComplianceCheck, Severity)> {
+        self.iter().find(|(check, _)| check.id() == id)
+    }
+
+    /// Checks with their pack severity, in evaluation order
+    pub fn iter(&self) -> impl Iterator<Item = (&dyn ComplianceCheck, Severity)> {
+        self.checks
+            .iter()
+            .map(|(check, severity)| (check.as_ref(), *severity))
+    }
+}
+
+/// Paths in a tree matching a gitignore-style glob
+///
+/// As in `.gitignore`, a pattern without a slash matches at any depth, a
+/// pattern containing one is anchored at the root, and a trailing slash only
+/// matches directories.
+pub fn matching_paths(pattern: &str, tree: &[TreeEntry]) -> Vec<String> {
+    let Some(glob) = gix::glob::parse(pattern) else {
+        return Vec::new();
+    };
+
+    tree.iter()
+        .filter(|entry| {
+            glob.matches_repo_relative_path(
+                entry.path.as_str().into(),
+                entry.path.rfind('/').map(|pos| pos + 1),
+                Some(entry.is_dir),
+                gix::glob::pattern::Case::Sensitive,
+                gix::glob::wildmatch::Mode::NO_MATCH_SLASH_LITERAL,
+            )
+        })
+        .map(|entry| entry.path.clone())
+        .collect()
+}
+
+/// Evaluate a required file or directory
+async fn evaluate_presence(
+    ctx: &CheckContext<'_>,
+    path: &str,
+    description: &str,
+) -> Result<Option<Evaluation>> {
+    Ok(Some(if ctx.source.file_exists(path).await? {
+        Evaluation::Pass(format!("{} found", description))
+    } else {
+        Evaluation::Missing(format!("{} missing", description))
+    }))
+}
+
+/// Evaluate a banned glob against the whole tree
+fn evaluate_banned(ctx: &CheckContext<'_>, pattern: &str, description: &str) -> Option<Evaluation> {
+    let paths = matching_paths(pattern, &ctx.tree.entries());
+
+    (!paths.is_empty()).then(|| Evaluation::Violation {
+        message: format!(
+            "{} detected - policy violation: {}",
+            description,
+            paths.join(", ")
+        ),
+        locations: paths.into_iter().map(Location::file).collect(),
+    })
+}
+
+#[async_trait]
+impl ComplianceCheck for FileRule {
+    fn id(&self) -> String {
+        self.path.clone()
+    }
+
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    fn category(&self) -> CheckCategory {
+        self.category
+    }
+
+    fn points(&self) -> u8 {
+        self.points
+    }
+
+    fn remediation(&self) -> String {
+        self.remediation.clone()
+    }
+
+    async fn evaluate(&self, ctx: &CheckContext<'_>) -> Result<Option<Evaluation>> {
+        evaluate_presence(ctx, &self.path, &self.description).await
+    }
+}
+
+#[async_trait]
+impl ComplianceCheck for BanRule {
+    fn id(&self) -> String {
+        format!("no-{}", self.pattern)
+    }
+
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    fn category(&self) -> CheckCategory {
+        self.category
+    }
+
+    fn points(&self) -> u8 {
+        0
+    }
+
+    fn remediation(&self) -> String {
+        self.remediation.clone()
+    }
+
+    async fn evaluate(&self, ctx: &CheckContext<'_>) -> Result<Option<Evaluation>> {
+        Ok(evaluate_banned(ctx, &self.pattern, &self.description))
+    }
+}
+
+/// Repository license must be on the approved list
+#[async_trait]
+impl ComplianceCheck for LicenseRule {
+    fn id(&self) -> String {
+        "license-type".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Approved license".to_string()
+    }
+
+    fn category(&self) -> CheckCategory {
+        CheckCategory::Governance
+    }
+
+    fn points(&self) -> u8 {
+        self.points
+    }
+
+    fn remediation(&self) -> String {
+        format!(
+            "License the repository under one of: {}.",
+            self.approved.join(", ")
+        )
+    }
+
+    async fn evaluate(&self, ctx: &CheckContext<'_>) -> Result<Option<Evaluation>> {
+        Ok(Some(match ctx.source.license().await? {
+            Some(license) if self.approved.contains(&license.key) => {
+                Evaluation::Pass(format!("Approved license: {}", license.name))
+            }
+            Some(license) => Evaluation::Partial {
+                points: 2,
+                message: format!("Non-standard license: {}", license.name),
+                locations: vec![Location::file(license.path)],
+            },
+            None => Evaluation::Missing("No license detected".to_string()),
+        }))
+    }
+}
+
+/// Extra required file from `.rsr.toml`
+struct RepoRequiredFile {
+    path: String,
+    description: String,
+    points: u8,
+}
+
+#[async_trait]
+impl ComplianceCheck for RepoRequiredFile {
+    fn id(&self) -> String {
+        self.path.clone()
+    }
+
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    fn category(&self) -> CheckCategory {
+        CheckCategory::Structure
+    }
+
+    fn points(&self) -> u8 {
+        self.points
+    }
+
+    fn remediation(&self) -> String {
+        format!("Add {} (required by .rsr.toml).", self.path)
+    }
+
+    async fn evaluate(&self, ctx: &CheckContext<'_>) -> Result<Option<Evaluation>> {
+        evaluate_presence(ctx, &self.path, &self.description).await
+    }
+}
+
+/// Extra banned pattern from `.rsr.toml`
+struct RepoBannedPattern {
+    pattern: String,
+    description: String,
+}
+
+#[async_trait]
+impl ComplianceCheck for RepoBannedPattern {
+    fn id(&self) -> String {
+        format!("no-{}", self.pattern)
+    }
+
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    fn category(&self) -> CheckCategory {
+        CheckCategory::LanguagePolicy
+    }
+
+    fn points(&self) -> u8 {
+        0
+    }
+
+    fn remediation(&self) -> String {
+        format!("Remove files matching {} (banned by .rsr.toml).", self.pattern)
+    }
+
+    async fn evaluate(&self, ctx: &CheckContext<'_>) -> Result<Option<Evaluation>> {
+        Ok(evaluate_banned(ctx, &self.pattern, &self.description))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::tests::MemorySource;
+
+    #[tokio::test]
+    async fn license_rule_fails_when_the_license_cannot_be_read() {
+        let rule = LicenseRule {
+            severity: Severity::Required,
+            points: 10,
+            approved: vec!["mit".to_string()],
+        };
+        let unlicensed = MemorySource::default();
+        let unavailable = MemorySource {
+            license_unavailable: true,
+            ..MemorySource::default()
+        };
+
+        let ctx = CheckContext {
+            source: &unlicensed,
+            tree: TreeIndex::default(),
+        };
+        assert!(matches!(
+            rule.evaluate(&ctx).await,
+            Ok(Some(Evaluation::Missing(_)))
+        ));
+
+        let ctx = CheckContext {
+            source: &unavailable,
+            tree: TreeIndex::default(),
+        };
+        assert!(rule.evaluate(&ctx).await.is_err());
+    }
+
+    #[test]
+    fn paths_match_like_gitignore() {
+        let tree: Vec<TreeEntry> = [
+            ("package-lock.json", false),
+            ("frontend", true),
+            ("frontend/package-lock.json", false),
+            ("frontend/node_modules", true),
+            ("node_modules", true),
+            ("src", true),
+            ("src/app.ts", false),
+            ("src/lib", true),
+            ("src/lib/util.ts", false),
+            ("src/node_modules", false),
+        ]
+        .into_iter()
+        .map(|(path, is_dir)| TreeEntry {
+            path: path.to_string(),
+            is_dir,
+        })
+        .collect();
+
+        let cases: [(&str, &[&str]); 8] = [
+            (
+                "package-lock.json",
+                &["package-lock.json", "frontend/package-lock.json"],
+            ),
+            ("/package-lock.json", &["package-lock.json"]),
+            ("*.ts", &["src/app.ts", "src/lib/util.ts"]),
+            ("src/*.ts", &["src/app.ts"]),
+            ("src/**/*.ts", &["src/app.ts", "src/lib/util.ts"]),
+            ("node_modules/", &["frontend/node_modules", "node_modules"]),
+            ("*.rs", &[]),
+            ("", &[]),
+        ];
+
+        for (pattern, expected) in cases {
+            assert_eq!(matching_paths(pattern, &tree), expected, "{}", pattern);
+        }
+    }
+}
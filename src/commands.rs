@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! One-shot CLI commands
+//!
+//! These run the same compliance engine the bot uses on webhooks, so it can
+//! be used from pre-commit hooks and CI.
+
+use anyhow::Result;
+use std::path::Path;
+use std::process::ExitCode;
+
+use crate::config::Config;
+use crate::github::GitHubClient;
+use crate::render::{self, ReportFormat};
+use crate::rsr;
+use crate::source::{GitHubSource, LocalSource};
+
+/// Check a repository given as a local path or `owner/repo`
+pub async fn check(
+    config: &Config,
+    target: &str,
+    rev: Option<&str>,
+    format: ReportFormat,
+) -> Result<ExitCode> {
+    let report = if Path::new(target).exists() {
+        let source = LocalSource::open(Path::new(target), rev)?;
+        rsr::check_compliance(&source).await?
+    } else {
+        let Some((owner, repo)) = target.split_once('/') else {
+            anyhow::bail!("{} is neither a local path nor owner/repo", target);
+        };
+        let client = GitHubClient::for_repo(config, owner, repo).await?;
+        let source = GitHubSource::new(&client, owner, repo, rev);
+        rsr::check_compliance(&source).await?
+    };
+
+    let output = render::render(&report, format)?;
+    if output.ends_with('\n') {
+        print!("{}", output);
+    } else {
+        println!("{}", output);
+    }
+
+    Ok(if report.required_passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Check every (non-archived) repository in an organization
+pub async fn scan_org(config: &Config, org: &str) -> Result<ExitCode> {
+    let client = GitHubClient::for_org(config, org).await?;
+    let repos = client.list_org_repos(org).await?;
+
+    let mut failed = 0usize;
+    let mut checked = 0usize;
+
+    for repo in repos.iter().filter(|r| !r.archived) {
+        let source = GitHubSource::new(&client, org, &repo.name, None);
+        match rsr::check_compliance(&source).await {
+            Ok(report) => {
+                checked += 1;
+                if !report.required_passed {
+                    failed += 1;
+                }
+                println!(
+                    "{:<4} {:>4.0}%  {:<10} {}/{}",
+                    if report.required_passed { "ok" } else { "FAIL" },
+                    report.percentage,
+                    report.policy,
+                    org,
+                    repo.name
+                );
+            }
+            Err(e) => {
+                failed += 1;
+                println!("ERR  {}/{}: {}", org, repo.name, e);
+            }
+        }
+    }
+
+    println!(
+        "\n{} repositories checked, {} failing required checks",
+        checked, failed
+    );
+
+    Ok(if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Print what a check verifies, its severity per policy pack and how to fix it
+pub fn explain(check: &str) -> Result<ExitCode> {
+    let Some(explanation) = rsr::explain_check(check) else {
+        eprintln!("Unknown check: {}", check);
+        return Ok(ExitCode::FAILURE);
+    };
+
+    println!("{}: {}", explanation.name, explanation.description);
+    println!("Category: {}", explanation.category);
+    if explanation.points > 0 {
+        println!("Points: {}", explanation.points);
+    }
+
+    println!("\nSeverity by policy pack:");
+    for (pack, severity) in &explanation.severities {
+        println!("  {:<10} {}", pack.to_string(), severity);
+    }
+
+    println!("\nHow to fix:\n  {}", explanation.remediation);
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! The sticky pull request comment
+//!
+//! Rhodibot keeps one comment per pull request summarizing its compliance,
+//! found again by a hidden marker and edited in place on every push.
+
+use anyhow::Result;
+use tracing::info;
+
+use crate::github::{GitHubClient, IssueComment};
+
+/// Hidden marker identifying rhodibot's comment
+pub const MARKER: &str = "<!-- rhodibot:rsr-comment -->";
+
+/// Create the comment on a pull request or replace its body
+///
+/// The marker is added to `body`. Only a comment posted by `bot`, the user
+/// rhodibot acts as, is taken for its own; anyone can quote the marker. An
+/// unchanged comment is left alone.
+pub async fn upsert(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    number: u64,
+    bot: &str,
+    body: &str,
+) -> Result<IssueComment> {
+    let body = format!("{}\n{}", MARKER, body);
+
+    let existing = client
+        .list_issue_comments(owner, repo, number)
+        .await?
+        .into_iter()
+        .find(|comment| is_own(comment, bot));
+
+    let comment = match existing {
+        Some(comment) if comment.body.as_deref() == Some(body.as_str()) => comment,
+        Some(comment) => {
+            let comment = client
+                .update_issue_comment(owner, repo, comment.id, &body)
+                .await?;
+            info!("Updated PR comment: {}", comment.html_url);
+            comment
+        }
+        None => {
+            let comment = client
+                .create_issue_comment(owner, repo, number, &body)
+                .await?;
+            info!("Created PR comment: {}", comment.html_url);
+            comment
+        }
+    };
+
+    Ok(comment)
+}
+
+/// Whether a comment is rhodibot's sticky comment
+fn is_own(comment: &IssueComment, bot: &str) -> bool {
+    comment.user.login == bot
+        && comment
+            .body
+            .as_deref()
+            .is_some_and(|body| body.contains(MARKER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::User;
+
+    #[test]
+    fn only_the_bots_marked_comment_is_its_own() {
+        let cases = [
+            ("rhodibot[bot]", Some(format!("{}\nScore", MARKER)), true),
+            ("rhodibot[bot]", Some("Thanks!".to_string()), false),
+            ("rhodibot[bot]", None, false),
+            ("mallory", Some(format!("{}\nFake score", MARKER)), false),
+            ("rhodibot", Some(format!("{}\nFake score", MARKER)), false),
+        ];
+
+        for (login, body, expected) in cases {
+            let comment = IssueComment {
+                id: 1,
+                html_url: String::new(),
+                body,
+                user: User {
+                    login: login.to_string(),
+                },
+            };
+            assert_eq!(is_own(&comment, "rhodibot[bot]"), expected, "{:?}", comment);
+        }
+    }
+}
@@ -3,6 +3,8 @@
 //! Configuration module for Rhodibot
 
 use anyhow::Result;
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// Application configuration
 #[derive(Debug, Clone)]
@@ -15,11 +17,29 @@ pub struct Config {
     pub webhook_secret: Option<String>,
     /// GitHub API base URL (for GitHub Enterprise)
     pub github_api_url: String,
+    /// Directory of policy pack documents replacing the embedded packs
+    pub policy_dir: Option<PathBuf>,
+    /// Directory of templates overriding the embedded ones
+    pub template_dir: Option<PathBuf>,
+    /// Number of workers processing webhook jobs
+    pub workers: usize,
+    /// Webhook jobs that may wait before deliveries are rejected
+    pub queue_capacity: usize,
+    /// Attempts per webhook job, with exponential backoff between them
+    pub job_attempts: u32,
+    /// File remembering processed webhook deliveries across restarts
+    pub delivery_log: Option<PathBuf>,
+    /// SQLite database recording the report of every check run
+    pub history_db: Option<PathBuf>,
+    /// How long a repository's badge is served from cache
+    pub badge_ttl: Duration,
+    /// Largest accepted difference between an event's timestamp and now
+    pub max_event_age: Option<Duration>,
 }
 
 impl Config {
-    /// Create configuration from CLI arguments
-    pub fn from_cli(cli: &crate::Cli) -> Result<Self> {
+    /// Create configuration from CLI arguments and the server options
+    pub fn from_cli(cli: &crate::Cli, serve: &crate::ServeArgs) -> Result<Self> {
         let private_key = if let Some(ref path) = cli.private_key_path {
             Some(std::fs::read_to_string(path)?)
         } else {
@@ -29,9 +49,19 @@ pub fn from_cli(cli: &crate::Cli) -> Result<Self> {
         Ok(Self {
             app_id: cli.app_id,
             private_key,
-            webhook_secret: cli.webhook_secret.clone(),
+            webhook_secret: serve.webhook_secret.clone(),
             github_api_url: std::env::var("GITHUB_API_URL")
                 .unwrap_or_else(|_| "https://api.github.com".to_string()),
+            policy_dir: cli.policy_dir.clone(),
+            template_dir: cli.template_dir.clone(),
+            workers: serve.workers,
+            queue_capacity: serve.queue_capacity,
+            job_attempts: serve.job_attempts,
+            delivery_log: serve.delivery_log.clone(),
+            history_db: serve.history_db.clone(),
+            badge_ttl: Duration::from_secs(serve.badge_ttl),
+            max_event_age: (serve.max_event_age > 0)
+                .then(|| Duration::from_secs(serve.max_event_age)),
         })
     }
 }
@@ -0,0 +1,170 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! Webhook delivery deduplication
+//!
+//! GitHub identifies every delivery with an `X-GitHub-Delivery` ID and reuses
+//! it when a delivery is redelivered. The most recent IDs are remembered so a
+//! redelivery is acknowledged without running its handler again. The store
+//! is bounded and kept in memory; given a file, it is also appended there and
+//! reloaded on startup.
+
+use anyhow::{Context, Result};
+use std::collections::{HashSet, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+/// Delivery IDs remembered by default
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+/// Recently seen delivery IDs
+pub struct DeliveryStore {
+    state: Mutex<State>,
+}
+
+struct State {
+    ids: HashSet<String>,
+    /// IDs oldest first, for eviction
+    order: VecDeque<String>,
+    capacity: usize,
+    log: Option<DeliveryLog>,
+}
+
+/// Append-only file of `+id` and `-id` lines, compacted as it grows
+struct DeliveryLog {
+    path: PathBuf,
+    file: File,
+    lines: usize,
+}
+
+impl DeliveryStore {
+    /// Open a store remembering up to `capacity` IDs, persisted to `path` if given
+    pub fn open(capacity: usize, path: Option<&Path>) -> Result<Self> {
+        let mut state = State {
+            ids: HashSet::new(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+            log: None,
+        };
+
+        if let Some(path) = path {
+            let mut lines = 0;
+
+            if path.exists() {
+                let file = File::open(path)
+                    .with_context(|| format!("Failed to open delivery log {}", path.display()))?;
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    lines += 1;
+                    match line.split_at_checked(1) {
+                        Some(("+", id)) => state.remember(id.to_string()),
+                        Some(("-", id)) => state.forget(id),
+                        _ => {}
+                    }
+                }
+                info!(
+                    "Loaded {} webhook delivery IDs from {}",
+                    state.ids.len(),
+                    path.display()
+                );
+            }
+
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open delivery log {}", path.display()))?;
+            state.log = Some(DeliveryLog {
+                path: path.to_path_buf(),
+                file,
+                lines,
+            });
+        }
+
+        Ok(Self {
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Record a delivery, `false` if it was already seen
+    pub fn insert(&self, id: &str) -> bool {
+        let mut state = self.state.lock().expect("delivery store poisoned");
+        if state.ids.contains(id) {
+            return false;
+        }
+
+        state.remember(id.to_string());
+        state.append(&format!("+{}", id));
+
+        true
+    }
+
+    /// Forget a delivery so a redelivery is processed again
+    pub fn remove(&self, id: &str) {
+        let mut state = self.state.lock().expect("delivery store poisoned");
+        if state.ids.contains(id) {
+            state.forget(id);
+            state.append(&format!("-{}", id));
+        }
+    }
+}
+
+impl State {
+    fn remember(&mut self, id: String) {
+        if self.ids.insert(id.clone()) {
+            self.order.push_back(id);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+    }
+
+    fn forget(&mut self, id: &str) {
+        if self.ids.remove(id) {
+            self.order.retain(|known| known != id);
+        }
+    }
+
+    /// Append a line to the log, rewriting it once it holds twice the capacity
+    fn append(&mut self, line: &str) {
+        let Some(log) = self.log.as_mut() else {
+            return;
+        };
+
+        if let Err(e) = writeln!(log.file, "{}", line) {
+            warn!("Failed to write delivery log {}: {}", log.path.display(), e);
+            return;
+        }
+        log.lines += 1;
+
+        if log.lines > self.capacity * 2
+            && let Err(e) = self.compact()
+        {
+            warn!("Failed to compact delivery log: {:#}", e);
+        }
+    }
+
+    /// Replace the log with the IDs currently remembered
+    fn compact(&mut self) -> Result<()> {
+        let Some(log) = self.log.as_mut() else {
+            return Ok(());
+        };
+
+        let tmp = log.path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        for id in &self.order {
+            writeln!(file, "+{}", id)?;
+        }
+        file.sync_all()?;
+        std::fs::rename(&tmp, &log.path)?;
+
+        log.file = OpenOptions::new().append(true).open(&log.path)?;
+        log.lines = self.order.len();
+
+        Ok(())
+    }
+}
@@ -3,10 +3,14 @@
 //! GitHub API client module
 
 use anyhow::Result;
-use reqwest::Client;
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
 use serde::{Deserialize, Serialize};
+use tracing::debug;
 
+use crate::auth;
 use crate::config::Config;
+use crate::metrics;
 
 /// GitHub API client
 pub struct GitHubClient {
@@ -16,88 +20,301 @@ pub struct GitHubClient {
 }
 
 impl GitHubClient {
-    /// Create a new GitHub client
+    /// Create a new GitHub client using the static `GITHUB_TOKEN`, if set
     pub fn new(config: &Config) -> Self {
+        Self::with_token(config, std::env::var("GITHUB_TOKEN").ok())
+    }
+
+    /// Create a GitHub client authenticating with the given bearer token
+    pub fn with_token(config: &Config, token: Option<String>) -> Self {
         Self {
             client: Client::new(),
             base_url: config.github_api_url.clone(),
-            token: std::env::var("GITHUB_TOKEN").ok(),
+            token,
         }
     }
 
-    /// Get repository information
-    pub async fn get_repository(&self, owner: &str, repo: &str) -> Result<Repository> {
-        let url = format!("{}/repos/{}/{}", self.base_url, owner, repo);
-        let mut request = self.client.get(&url);
+    /// Create a client acting as the GitHub App installation, if known
+    ///
+    /// Falls back to [`GitHubClient::new`] when no installation is given or
+    /// no App credentials are configured.
+    pub async fn for_installation(config: &Config, installation_id: Option<u64>) -> Result<Self> {
+        match installation_id {
+            Some(id) if config.app_id.is_some() && config.private_key.is_some() => {
+                let token = auth::installation_token(config, id).await?;
+                Ok(Self::with_token(config, Some(token)))
+            }
+            _ => Ok(Self::new(config)),
+        }
+    }
+
+    /// Create a client for a repository, resolving the App installation on it
+    ///
+    /// Falls back to [`GitHubClient::new`] when no App credentials are
+    /// configured or the App is not installed on the repository, and fails if
+    /// the installation cannot be looked up.
+    pub async fn for_repo(config: &Config, owner: &str, repo: &str) -> Result<Self> {
+        if config.app_id.is_none() || config.private_key.is_none() {
+            return Ok(Self::new(config));
+        }
+
+        match auth::repo_installation_id(config, owner, repo).await? {
+            Some(id) => Self::for_installation(config, Some(id)).await,
+            None => {
+                debug!("No App installation for {}/{}", owner, repo);
+                Ok(Self::new(config))
+            }
+        }
+    }
+
+    /// Create a client for a repository the App is installed on
+    ///
+    /// `None` if App credentials are configured but the App is not installed
+    /// on the repository, so nothing is requested on its behalf. Without App
+    /// credentials every repository is served with [`GitHubClient::new`].
+    pub async fn for_installed_repo(
+        config: &Config,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Option<Self>> {
+        if config.app_id.is_none() || config.private_key.is_none() {
+            return Ok(Some(Self::new(config)));
+        }
+
+        match auth::repo_installation_id(config, owner, repo).await? {
+            Some(id) => Ok(Some(Self::for_installation(config, Some(id)).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Create a client for an organization, resolving the App installation on it
+    pub async fn for_org(config: &Config, org: &str) -> Result<Self> {
+        if config.app_id.is_none() || config.private_key.is_none() {
+            return Ok(Self::new(config));
+        }
+
+        match auth::org_installation_id(config, org).await? {
+            Some(id) => Self::for_installation(config, Some(id)).await,
+            None => {
+                debug!("No App installation for {}", org);
+                Ok(Self::new(config))
+            }
+        }
+    }
+
+    /// Build an authenticated request against the API
+    fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        self.request_accepting(method, url, "application/vnd.github+json")
+    }
+
+    /// Build an authenticated request asking for a specific media type
+    fn request_accepting(&self, method: Method, url: &str, accept: &str) -> RequestBuilder {
+        let mut request = self.client.request(method, url);
 
         if let Some(ref token) = self.token {
             request = request.bearer_auth(token);
         }
 
-        let response = request
-            .header("Accept", "application/vnd.github+json")
+        request
+            .header("Accept", accept)
             .header("User-Agent", "rhodibot")
-            .send()
-            .await?;
+    }
+
+    /// Get the App authenticating the request
+    pub async fn get_app(&self) -> Result<App> {
+        let url = format!("{}/app", self.base_url);
+
+        let response = self.request(Method::GET, &url).send_metered().await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to get app: {}", response.status());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Get the user the token belongs to
+    pub async fn get_authenticated_user(&self) -> Result<User> {
+        let url = format!("{}/user", self.base_url);
+
+        let response = self.request(Method::GET, &url).send_metered().await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to get authenticated user: {}", response.status());
+        }
 
         Ok(response.json().await?)
     }
 
-    /// Get repository contents
-    pub async fn get_contents(
+    /// Exchange the App JWT for an installation access token
+    pub async fn create_installation_token(&self, installation_id: u64) -> Result<AccessToken> {
+        let url = format!(
+            "{}/app/installations/{}/access_tokens",
+            self.base_url, installation_id
+        );
+
+        let response = self.request(Method::POST, &url).send_metered().await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Failed to create installation token: {}",
+                response.status()
+            );
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Get the App installation for a repository, `None` if the App is not installed
+    pub async fn get_repo_installation(
         &self,
         owner: &str,
         repo: &str,
-        path: &str,
-    ) -> Result<Vec<ContentItem>> {
-        let url = format!("{}/repos/{}/{}/contents/{}", self.base_url, owner, repo, path);
-        let mut request = self.client.get(&url);
+    ) -> Result<Option<AppInstallation>> {
+        let url = format!("{}/repos/{}/{}/installation", self.base_url, owner, repo);
 
-        if let Some(ref token) = self.token {
-            request = request.bearer_auth(token);
+        let response = self.request(Method::GET, &url).send_metered().await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to get repository installation: {}", response.status());
         }
 
-        let response = request
-            .header("Accept", "application/vnd.github+json")
-            .header("User-Agent", "rhodibot")
-            .send()
-            .await?;
+        Ok(Some(response.json().await?))
+    }
 
-        Ok(response.json().await?)
+    /// Get the App installation for an organization, `None` if the App is not installed
+    pub async fn get_org_installation(&self, org: &str) -> Result<Option<AppInstallation>> {
+        let url = format!("{}/orgs/{}/installation", self.base_url, org);
+
+        let response = self.request(Method::GET, &url).send_metered().await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to get organization installation: {}", response.status());
+        }
+
+        Ok(Some(response.json().await?))
     }
 
-    /// Check if a file exists
-    pub async fn file_exists(&self, owner: &str, repo: &str, path: &str) -> bool {
-        let url = format!("{}/repos/{}/{}/contents/{}", self.base_url, owner, repo, path);
-        let mut request = self.client.head(&url);
+    /// List every repository in an organization
+    pub async fn list_org_repos(&self, org: &str) -> Result<Vec<Repository>> {
+        let mut repos = Vec::new();
 
-        if let Some(ref token) = self.token {
-            request = request.bearer_auth(token);
+        for page in 1.. {
+            let url = format!(
+                "{}/orgs/{}/repos?per_page=100&page={}",
+                self.base_url, org, page
+            );
+
+            let response = self.request(Method::GET, &url).send_metered().await?;
+
+            if !response.status().is_success() {
+                anyhow::bail!("Failed to list organization repositories: {}", response.status());
+            }
+
+            let batch: Vec<Repository> = response.json().await?;
+            let done = batch.len() < 100;
+            repos.extend(batch);
+
+            if done {
+                break;
+            }
         }
 
-        request
-            .header("User-Agent", "rhodibot")
-            .send()
-            .await
-            .map(|r| r.status().is_success())
-            .unwrap_or(false)
+        Ok(repos)
     }
 
-    /// Get file content (decoded from base64)
-    pub async fn get_file_content(&self, owner: &str, repo: &str, path: &str) -> Result<String> {
-        let url = format!("{}/repos/{}/{}/contents/{}", self.base_url, owner, repo, path);
-        let mut request = self.client.get(&url);
+    /// Get repository information
+    pub async fn get_repository(&self, owner: &str, repo: &str) -> Result<Repository> {
+        let url = format!("{}/repos/{}/{}", self.base_url, owner, repo);
 
-        if let Some(ref token) = self.token {
-            request = request.bearer_auth(token);
+        let response = self.request(Method::GET, &url).send_metered().await?;
+
+        Ok(response.json().await?)
+    }
+
+    /// Get the license file detected at a ref, `None` if there is none
+    pub async fn get_license(
+        &self,
+        owner: &str,
+        repo: &str,
+        git_ref: Option<&str>,
+    ) -> Result<Option<LicenseContent>> {
+        let url = format!("{}/repos/{}/{}/license", self.base_url, owner, repo);
+
+        let response = with_ref(self.request(Method::GET, &url), git_ref)
+            .send_metered()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to get license: {}", response.status());
         }
 
-        let response = request
-            .header("Accept", "application/vnd.github.raw+json")
-            .header("User-Agent", "rhodibot")
-            .send()
+        Ok(Some(response.json().await?))
+    }
+
+    /// Resolve a branch, tag or SHA to a commit SHA
+    pub async fn get_commit_sha(&self, owner: &str, repo: &str, git_ref: &str) -> Result<String> {
+        let url = format!("{}/repos/{}/{}/commits/{}", self.base_url, owner, repo, git_ref);
+
+        let response = self
+            .request_accepting(Method::GET, &url, "application/vnd.github.sha")
+            .send_metered()
             .await?;
 
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to resolve {}: {}", git_ref, response.status());
+        }
+
+        Ok(response.text().await?.trim().to_string())
+    }
+
+    /// Get a git tree by SHA or ref, optionally with all nested entries
+    pub async fn get_tree(
+        &self,
+        owner: &str,
+        repo: &str,
+        tree_ish: &str,
+        recursive: bool,
+    ) -> Result<GitTree> {
+        let mut url = format!(
+            "{}/repos/{}/{}/git/trees/{}",
+            self.base_url, owner, repo, tree_ish
+        );
+        if recursive {
+            url.push_str("?recursive=1");
+        }
+
+        let response = self.request(Method::GET, &url).send_metered().await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to get tree: {}", response.status());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Get file content at a ref (the default branch if `None`)
+    pub async fn get_file_content(
+        &self,
+        owner: &str,
+        repo: &str,
+        path: &str,
+        git_ref: Option<&str>,
+    ) -> Result<String> {
+        let url = format!("{}/repos/{}/{}/contents/{}", self.base_url, owner, repo, path);
+
+        let request = self.request_accepting(Method::GET, &url, "application/vnd.github.raw+json");
+        let response = with_ref(request, git_ref).send_metered().await?;
+
         if !response.status().is_success() {
             anyhow::bail!("Failed to get file content: {}", response.status());
         }
@@ -115,11 +332,6 @@ pub async fn create_issue(
         labels: &[&str],
     ) -> Result<Issue> {
         let url = format!("{}/repos/{}/{}/issues", self.base_url, owner, repo);
-        let mut request = self.client.post(&url);
-
-        if let Some(ref token) = self.token {
-            request = request.bearer_auth(token);
-        }
 
         let payload = CreateIssue {
             title: title.to_string(),
@@ -127,17 +339,211 @@ pub async fn create_issue(
             labels: labels.iter().map(|s| s.to_string()).collect(),
         };
 
-        let response = request
-            .header("Accept", "application/vnd.github+json")
-            .header("User-Agent", "rhodibot")
+        let response = self
+            .request(Method::POST, &url)
             .json(&payload)
-            .send()
+            .send_metered()
+            .await?;
+
+        Ok(response.json().await?)
+    }
+
+    /// List issues (not pull requests) with a label, open and closed
+    pub async fn list_issues_with_label(
+        &self,
+        owner: &str,
+        repo: &str,
+        label: &str,
+    ) -> Result<Vec<Issue>> {
+        let mut issues = Vec::new();
+
+        for page in 1.. {
+            let url = format!("{}/repos/{}/{}/issues", self.base_url, owner, repo);
+            let page = page.to_string();
+
+            let response = self
+                .request(Method::GET, &url)
+                .query(&[
+                    ("labels", label),
+                    ("state", "all"),
+                    ("per_page", "100"),
+                    ("page", page.as_str()),
+                ])
+                .send_metered()
+                .await?;
+
+            if !response.status().is_success() {
+                anyhow::bail!("Failed to list issues: {}", response.status());
+            }
+
+            let batch: Vec<Issue> = response.json().await?;
+            let done = batch.len() < 100;
+            issues.extend(batch.into_iter().filter(|issue| issue.pull_request.is_none()));
+
+            if done {
+                break;
+            }
+        }
+
+        Ok(issues)
+    }
+
+    /// Search issues (not pull requests) of a repository, open and closed
+    ///
+    /// `terms` uses the GitHub search syntax, e.g. `"some text" in:body`.
+    pub async fn search_issues(&self, owner: &str, repo: &str, terms: &str) -> Result<Vec<Issue>> {
+        let url = format!("{}/search/issues", self.base_url);
+        let query = format!("repo:{}/{} is:issue {}", owner, repo, terms);
+
+        let response = self
+            .request(Method::GET, &url)
+            .query(&[("q", query.as_str()), ("per_page", "100")])
+            .send_metered()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to search issues: {}", response.status());
+        }
+
+        let results: IssueSearchResults = response.json().await?;
+
+        Ok(results.items)
+    }
+
+    /// Update the fields of an issue that are set in `update`
+    pub async fn update_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        update: &UpdateIssue,
+    ) -> Result<Issue> {
+        let url = format!("{}/repos/{}/{}/issues/{}", self.base_url, owner, repo, number);
+
+        let response = self
+            .request(Method::PATCH, &url)
+            .json(update)
+            .send_metered()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to update issue #{}: {}", number, response.status());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Close an issue as completed
+    pub async fn close_issue(&self, owner: &str, repo: &str, number: u64) -> Result<Issue> {
+        let update = UpdateIssue {
+            state: Some("closed".to_string()),
+            state_reason: Some("completed".to_string()),
+            ..Default::default()
+        };
+
+        self.update_issue(owner, repo, number, &update).await
+    }
+
+    /// Reopen a closed issue
+    pub async fn reopen_issue(&self, owner: &str, repo: &str, number: u64) -> Result<Issue> {
+        let update = UpdateIssue {
+            state: Some("open".to_string()),
+            state_reason: Some("reopened".to_string()),
+            ..Default::default()
+        };
+
+        self.update_issue(owner, repo, number, &update).await
+    }
+
+    /// List the comments on an issue or pull request
+    pub async fn list_issue_comments(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<Vec<IssueComment>> {
+        let mut comments = Vec::new();
+
+        for page in 1.. {
+            let url = format!(
+                "{}/repos/{}/{}/issues/{}/comments?per_page=100&page={}",
+                self.base_url, owner, repo, number, page
+            );
+
+            let response = self.request(Method::GET, &url).send_metered().await?;
+
+            if !response.status().is_success() {
+                anyhow::bail!("Failed to list comments on #{}: {}", number, response.status());
+            }
+
+            let batch: Vec<IssueComment> = response.json().await?;
+            let done = batch.len() < 100;
+            comments.extend(batch);
+
+            if done {
+                break;
+            }
+        }
+
+        Ok(comments)
+    }
+
+    /// Comment on an issue or pull request
+    pub async fn create_issue_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        body: &str,
+    ) -> Result<IssueComment> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}/comments",
+            self.base_url, owner, repo, number
+        );
+
+        let response = self
+            .request(Method::POST, &url)
+            .json(&CommentBody { body })
+            .send_metered()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to comment on #{}: {}", number, response.status());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Replace the body of a comment
+    pub async fn update_issue_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        comment_id: u64,
+        body: &str,
+    ) -> Result<IssueComment> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/comments/{}",
+            self.base_url, owner, repo, comment_id
+        );
+
+        let response = self
+            .request(Method::PATCH, &url)
+            .json(&CommentBody { body })
+            .send_metered()
             .await?;
 
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to update comment {}: {}", comment_id, response.status());
+        }
+
         Ok(response.json().await?)
     }
 
     /// Create a check run
+    ///
+    /// GitHub accepts at most [`MAX_ANNOTATIONS`] annotations per request, so
+    /// any beyond the first batch are added with follow-up updates.
     pub async fn create_check_run(
         &self,
         owner: &str,
@@ -145,33 +551,442 @@ pub async fn create_check_run(
         check_run: &CreateCheckRun,
     ) -> Result<CheckRun> {
         let url = format!("{}/repos/{}/{}/check-runs", self.base_url, owner, repo);
-        let mut request = self.client.post(&url);
 
-        if let Some(ref token) = self.token {
-            request = request.bearer_auth(token);
+        let (first, rest) = match &check_run.output {
+            Some(output) => output.batched(),
+            None => (None, Vec::new()),
+        };
+        let payload = CreateCheckRun {
+            output: first,
+            ..check_run.clone()
+        };
+
+        let response = self
+            .request(Method::POST, &url)
+            .json(&payload)
+            .send_metered()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to create check run: {}", response.status());
         }
 
-        let response = request
-            .header("Accept", "application/vnd.github+json")
-            .header("User-Agent", "rhodibot")
-            .json(check_run)
-            .send()
+        let created: CheckRun = response.json().await?;
+        self.append_annotations(owner, repo, created.id, rest)
             .await?;
 
+        Ok(created)
+    }
+
+    /// List the latest check runs of every app on a commit
+    pub async fn list_check_runs(&self, owner: &str, repo: &str, sha: &str) -> Result<Vec<CheckRun>> {
+        let url = format!(
+            "{}/repos/{}/{}/commits/{}/check-runs",
+            self.base_url, owner, repo, sha
+        );
+
+        let response = self
+            .request(Method::GET, &url)
+            .query(&[("filter", "latest"), ("per_page", "100")])
+            .send_metered()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to list check runs of {}: {}", sha, response.status());
+        }
+
+        let list: CheckRunList = response.json().await?;
+
+        Ok(list.check_runs)
+    }
+
+    /// Update the fields of a check run that are set in `update`
+    ///
+    /// Annotations are batched as in [`Self::create_check_run`].
+    pub async fn update_check_run(
+        &self,
+        owner: &str,
+        repo: &str,
+        check_run_id: u64,
+        update: &UpdateCheckRun,
+    ) -> Result<CheckRun> {
+        let (first, rest) = match &update.output {
+            Some(output) => output.batched(),
+            None => (None, Vec::new()),
+        };
+        let payload = UpdateCheckRun {
+            output: first,
+            ..update.clone()
+        };
+
+        let updated = self
+            .patch_check_run(owner, repo, check_run_id, &payload)
+            .await?;
+        self.append_annotations(owner, repo, check_run_id, rest)
+            .await?;
+
+        Ok(updated)
+    }
+
+    /// Send follow-up outputs carrying annotations beyond the first batch
+    async fn append_annotations(
+        &self,
+        owner: &str,
+        repo: &str,
+        check_run_id: u64,
+        outputs: Vec<CheckRunOutput>,
+    ) -> Result<()> {
+        for output in outputs {
+            let update = UpdateCheckRun {
+                output: Some(output),
+                ..Default::default()
+            };
+            self.patch_check_run(owner, repo, check_run_id, &update)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn patch_check_run(
+        &self,
+        owner: &str,
+        repo: &str,
+        check_run_id: u64,
+        update: &UpdateCheckRun,
+    ) -> Result<CheckRun> {
+        let url = format!(
+            "{}/repos/{}/{}/check-runs/{}",
+            self.base_url, owner, repo, check_run_id
+        );
+
+        let response = self
+            .request(Method::PATCH, &url)
+            .json(update)
+            .send_metered()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to update check run {}: {}", check_run_id, response.status());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Get a git reference (e.g. `heads/main`), `None` if it does not exist
+    pub async fn get_ref(&self, owner: &str, repo: &str, git_ref: &str) -> Result<Option<GitRef>> {
+        let url = format!("{}/repos/{}/{}/git/ref/{}", self.base_url, owner, repo, git_ref);
+
+        let response = self.request(Method::GET, &url).send_metered().await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to get ref {}: {}", git_ref, response.status());
+        }
+
+        Ok(Some(response.json().await?))
+    }
+
+    /// Create a git reference (e.g. `refs/heads/feature`) pointing at a commit
+    pub async fn create_ref(&self, owner: &str, repo: &str, git_ref: &str, sha: &str) -> Result<GitRef> {
+        let url = format!("{}/repos/{}/{}/git/refs", self.base_url, owner, repo);
+
+        let payload = CreateRef {
+            git_ref: git_ref.to_string(),
+            sha: sha.to_string(),
+        };
+
+        let response = self
+            .request(Method::POST, &url)
+            .json(&payload)
+            .send_metered()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to create ref {}: {}", git_ref, response.status());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Move a git reference (e.g. `heads/feature`) to a commit
+    pub async fn update_ref(
+        &self,
+        owner: &str,
+        repo: &str,
+        git_ref: &str,
+        sha: &str,
+        force: bool,
+    ) -> Result<GitRef> {
+        let url = format!("{}/repos/{}/{}/git/refs/{}", self.base_url, owner, repo, git_ref);
+
+        let payload = UpdateRef {
+            sha: sha.to_string(),
+            force,
+        };
+
+        let response = self
+            .request(Method::PATCH, &url)
+            .json(&payload)
+            .send_metered()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to update ref {}: {}", git_ref, response.status());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Get a git commit object
+    pub async fn get_git_commit(&self, owner: &str, repo: &str, sha: &str) -> Result<GitCommit> {
+        let url = format!("{}/repos/{}/{}/git/commits/{}", self.base_url, owner, repo, sha);
+
+        let response = self.request(Method::GET, &url).send_metered().await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to get commit {}: {}", sha, response.status());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Create a tree adding files on top of `base_tree`
+    pub async fn create_tree(
+        &self,
+        owner: &str,
+        repo: &str,
+        base_tree: &str,
+        files: &[NewTreeFile],
+    ) -> Result<GitTree> {
+        let url = format!("{}/repos/{}/{}/git/trees", self.base_url, owner, repo);
+
+        let payload = CreateTree {
+            base_tree: base_tree.to_string(),
+            tree: files,
+        };
+
+        let response = self
+            .request(Method::POST, &url)
+            .json(&payload)
+            .send_metered()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to create tree: {}", response.status());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Create a commit of a tree
+    pub async fn create_commit(
+        &self,
+        owner: &str,
+        repo: &str,
+        message: &str,
+        tree: &str,
+        parents: &[&str],
+    ) -> Result<GitCommit> {
+        let url = format!("{}/repos/{}/{}/git/commits", self.base_url, owner, repo);
+
+        let payload = CreateCommit {
+            message: message.to_string(),
+            tree: tree.to_string(),
+            parents: parents.iter().map(|s| s.to_string()).collect(),
+        };
+
+        let response = self
+            .request(Method::POST, &url)
+            .json(&payload)
+            .send_metered()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to create commit: {}", response.status());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// List pull requests from a branch of the repository itself, newest first
+    ///
+    /// `state` is `open`, `closed` or `all`.
+    pub async fn list_pulls_from(
+        &self,
+        owner: &str,
+        repo: &str,
+        branch: &str,
+        state: &str,
+    ) -> Result<Vec<PullRequest>> {
+        let url = format!("{}/repos/{}/{}/pulls", self.base_url, owner, repo);
+        let head = format!("{}:{}", owner, branch);
+
+        let response = self
+            .request(Method::GET, &url)
+            .query(&[("state", state), ("head", head.as_str())])
+            .send_metered()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to list pull requests: {}", response.status());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Open a pull request
+    pub async fn create_pull(&self, owner: &str, repo: &str, pull: &CreatePull) -> Result<PullRequest> {
+        let url = format!("{}/repos/{}/{}/pulls", self.base_url, owner, repo);
+
+        let response = self
+            .request(Method::POST, &url)
+            .json(pull)
+            .send_metered()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to create pull request: {}", response.status());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Update the title and body of a pull request
+    pub async fn update_pull(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let url = format!("{}/repos/{}/{}/pulls/{}", self.base_url, owner, repo, number);
+
+        let payload = UpdatePull {
+            title: title.to_string(),
+            body: body.to_string(),
+        };
+
+        let response = self
+            .request(Method::PATCH, &url)
+            .json(&payload)
+            .send_metered()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to update pull request #{}: {}", number, response.status());
+        }
+
         Ok(response.json().await?)
     }
 }
 
+/// Sending API requests while counting them in the metrics
+trait SendMetered {
+    /// Send the request, recording its endpoint, status and the rate limit left
+    async fn send_metered(self) -> reqwest::Result<Response>;
+}
+
+impl SendMetered for RequestBuilder {
+    async fn send_metered(self) -> reqwest::Result<Response> {
+        let (client, request) = self.build_split();
+        let request = request?;
+        let endpoint = format!("{} {}", request.method(), endpoint(request.url().path()));
+
+        let result = client.execute(request).await;
+        match &result {
+            Ok(response) => {
+                let headers = response.headers();
+                let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+                if let Some(remaining) = header("x-ratelimit-remaining").and_then(|v| v.parse().ok()) {
+                    let resource = header("x-ratelimit-resource").unwrap_or("core");
+                    metrics::rate_limit_remaining(resource, remaining);
+                }
+                metrics::github_request(endpoint, response.status().as_u16().to_string());
+            }
+            Err(_) => metrics::github_request(endpoint, "error".to_string()),
+        }
+
+        result
+    }
+}
+
+/// Path template of an API URL path, e.g. `/repos/{owner}/{repo}/issues/{id}`
+///
+/// Keeps the endpoint label of the request metrics to a bounded set.
+fn endpoint(path: &str) -> String {
+    let mut segments = path.split('/').filter(|segment| !segment.is_empty());
+    let mut template: Vec<&str> = Vec::new();
+
+    while let Some(segment) = segments.next() {
+        let placeholder = match template.last().copied() {
+            Some("repos") if segments.clone().next().is_some() => {
+                segments.next();
+                template.push("{owner}");
+                "{repo}"
+            }
+            Some("orgs" | "users") => "{owner}",
+            Some("contents") => {
+                segments.by_ref().for_each(drop);
+                "{path}"
+            }
+            Some("refs" | "ref") => {
+                segments.by_ref().for_each(drop);
+                "{ref}"
+            }
+            Some("commits" | "trees" | "blobs" | "branches") => "{ref}",
+            _ if segment.bytes().all(|b| b.is_ascii_digit()) => "{id}",
+            _ => segment,
+        };
+        template.push(placeholder);
+    }
+
+    format!("/{}", template.join("/"))
+}
+
+/// Add a `ref` query parameter to a contents request, if one is given
+fn with_ref(request: RequestBuilder, git_ref: Option<&str>) -> RequestBuilder {
+    match git_ref {
+        Some(git_ref) => request.query(&[("ref", git_ref)]),
+        None => request,
+    }
+}
+
 #[derive(Debug, Deserialize)]
-pub struct Repository {
+pub struct AccessToken {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AppInstallation {
     pub id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct App {
+    /// Name in URLs; the App's bot user is `<slug>[bot]`
+    pub slug: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
     pub name: String,
     pub full_name: String,
     pub description: Option<String>,
     pub default_branch: String,
-    pub language: Option<String>,
-    pub topics: Vec<String>,
     pub license: Option<License>,
+    #[serde(default)]
+    pub archived: bool,
+    pub private: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -181,12 +996,27 @@ pub struct License {
     pub spdx_id: Option<String>,
 }
 
+/// License file detected in a repository
 #[derive(Debug, Deserialize)]
-pub struct ContentItem {
-    pub name: String,
+pub struct LicenseContent {
+    pub path: String,
+    pub license: Option<License>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitTree {
+    pub sha: String,
+    pub tree: Vec<GitTreeEntry>,
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitTreeEntry {
     pub path: String,
     #[serde(rename = "type")]
-    pub item_type: String,
+    pub entry_type: String,
+    pub sha: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -198,31 +1028,311 @@ struct CreateIssue {
 
 #[derive(Debug, Deserialize)]
 pub struct Issue {
-    pub id: u64,
     pub number: u64,
     pub title: String,
     pub html_url: String,
+    /// `open` or `closed`
+    pub state: String,
+    pub body: Option<String>,
+    pub user: User,
+    /// Set when the issue is a pull request
+    pub pull_request: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IssueComment {
+    pub id: u64,
+    pub html_url: String,
+    pub body: Option<String>,
+    pub user: User,
 }
 
 #[derive(Debug, Serialize)]
+struct CommentBody<'a> {
+    body: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueSearchResults {
+    items: Vec<Issue>,
+}
+
+/// Changes to an issue, unset fields are left as they are
+#[derive(Debug, Default, Serialize)]
+pub struct UpdateIssue {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// `open` or `closed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    /// `completed`, `not_planned` or `reopened`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct CreateCheckRun {
     pub name: String,
     pub head_sha: String,
+    /// `queued`, `in_progress` or `completed`
     pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub conclusion: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub output: Option<CheckRunOutput>,
+    /// Buttons shown on the check run, reported back as `requested_action` events
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<CheckRunAction>,
 }
 
-#[derive(Debug, Serialize)]
+/// Changes to a check run, unset fields are left as they are
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UpdateCheckRun {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conclusion: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<CheckRunOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actions: Option<Vec<CheckRunAction>>,
+}
+
+/// A check-run button
+///
+/// GitHub limits the label to 20, the description to 40 and the identifier
+/// to 20 characters.
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckRunAction {
+    pub label: String,
+    pub description: String,
+    pub identifier: String,
+}
+
+/// Most annotations GitHub accepts in one check run request
+pub const MAX_ANNOTATIONS: usize = 50;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct CheckRunOutput {
     pub title: String,
     pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<CheckAnnotation>,
+}
+
+impl CheckRunOutput {
+    /// Split into the output to send first and follow-ups carrying the
+    /// remaining annotations, at most [`MAX_ANNOTATIONS`] per output
+    fn batched(&self) -> (Option<CheckRunOutput>, Vec<CheckRunOutput>) {
+        let mut chunks = self.annotations.chunks(MAX_ANNOTATIONS);
+
+        let first = CheckRunOutput {
+            annotations: chunks.next().map(<[_]>::to_vec).unwrap_or_default(),
+            ..self.clone()
+        };
+        // Annotations are appended; title and summary are required on every update
+        let rest = chunks
+            .map(|chunk| CheckRunOutput {
+                title: self.title.clone(),
+                summary: self.summary.clone(),
+                text: None,
+                annotations: chunk.to_vec(),
+            })
+            .collect();
+
+        (Some(first), rest)
+    }
+}
+
+/// A message attached to lines of a file in the check run
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckAnnotation {
+    pub path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    /// `notice`, `warning` or `failure`
+    pub annotation_level: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CheckRun {
     pub id: u64,
     pub name: String,
-    pub status: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckRunList {
+    check_runs: Vec<CheckRun>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitRef {
+    pub object: GitObject,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitObject {
+    pub sha: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateRef {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    sha: String,
+}
+
+#[derive(Debug, Serialize)]
+struct UpdateRef {
+    sha: String,
+    force: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitCommit {
+    pub sha: String,
+    pub message: String,
+    pub tree: GitCommitTree,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitCommitTree {
+    pub sha: String,
+}
+
+/// File to add when creating a tree
+#[derive(Debug, Serialize)]
+pub struct NewTreeFile {
+    pub path: String,
+    pub mode: String,
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    pub content: String,
+}
+
+impl NewTreeFile {
+    /// Regular (non-executable) file with UTF-8 content
+    pub fn text(path: &str, content: String) -> Self {
+        Self {
+            path: path.to_string(),
+            mode: "100644".to_string(),
+            entry_type: "blob".to_string(),
+            content,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CreateTree<'a> {
+    base_tree: String,
+    tree: &'a [NewTreeFile],
+}
+
+#[derive(Debug, Serialize)]
+struct CreateCommit {
+    message: String,
+    tree: String,
+    parents: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatePull {
+    pub title: String,
+    pub head: String,
+    pub base: String,
+    pub body: String,
+}
+
+#[derive(Debug, Serialize)]
+struct UpdatePull {
+    title: String,
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub html_url: String,
+    pub state: String,
+    pub merged_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_are_path_templates() {
+        let cases = [
+            ("/repos/o/r", "/repos/{owner}/{repo}"),
+            ("/repos/o/r/pulls", "/repos/{owner}/{repo}/pulls"),
+            (
+                "/repos/o/r/issues/12/comments",
+                "/repos/{owner}/{repo}/issues/{id}/comments",
+            ),
+            (
+                "/repos/o/r/check-runs/7",
+                "/repos/{owner}/{repo}/check-runs/{id}",
+            ),
+            (
+                "/repos/o/r/contents/.github/workflows/ci.yml",
+                "/repos/{owner}/{repo}/contents/{path}",
+            ),
+            (
+                "/repos/o/r/git/ref/heads/rhodibot/rsr-scaffold",
+                "/repos/{owner}/{repo}/git/ref/{ref}",
+            ),
+            ("/repos/o/r/git/refs", "/repos/{owner}/{repo}/git/refs"),
+            (
+                "/repos/o/r/git/trees/abc123",
+                "/repos/{owner}/{repo}/git/trees/{ref}",
+            ),
+            (
+                "/repos/o/r/commits/main",
+                "/repos/{owner}/{repo}/commits/{ref}",
+            ),
+            ("/orgs/acme/installation", "/orgs/{owner}/installation"),
+            (
+                "/app/installations/42/access_tokens",
+                "/app/installations/{id}/access_tokens",
+            ),
+            ("/rate_limit", "/rate_limit"),
+            ("/", "/"),
+        ];
+
+        for (path, expected) in cases {
+            assert_eq!(endpoint(path), expected, "{}", path);
+        }
+    }
+
+    #[test]
+    fn contents_requests_are_pinned_to_the_ref() {
+        let url = |git_ref| {
+            let request =
+                Client::new().get("https://api.github.com/repos/o/r/contents/README.adoc");
+            with_ref(request, git_ref)
+                .build()
+                .unwrap()
+                .url()
+                .to_string()
+        };
+
+        assert_eq!(
+            url(Some("feature/security-policy")),
+            "https://api.github.com/repos/o/r/contents/README.adoc?ref=feature%2Fsecurity-policy"
+        );
+        assert_eq!(
+            url(None),
+            "https://api.github.com/repos/o/r/contents/README.adoc"
+        );
+    }
 }
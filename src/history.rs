@@ -0,0 +1,488 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! Report history
+//!
+//! The report behind every check run is stored in an SQLite database with
+//! its repository, ref, commit, policy pack, score and the status of each
+//! check, so compliance can be followed over time. The schema is created and
+//! upgraded by numbered migrations, tracked in the database's `user_version`.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, Row, params};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::info;
+
+use crate::rsr::{BaseCheck, ComplianceReport, report_conclusion};
+
+/// Schema migrations, applied in order; the database records how many ran
+const MIGRATIONS: &[&str] = &[
+    // 1: reports and the status of their checks
+    "CREATE TABLE reports (
+        id INTEGER PRIMARY KEY,
+        owner TEXT NOT NULL COLLATE NOCASE,
+        repo TEXT NOT NULL COLLATE NOCASE,
+        git_ref TEXT,
+        commit_sha TEXT,
+        policy TEXT NOT NULL,
+        pack_version TEXT NOT NULL,
+        score INTEGER NOT NULL,
+        max_score INTEGER NOT NULL,
+        percentage REAL NOT NULL,
+        required_passed INTEGER NOT NULL,
+        conclusion TEXT NOT NULL,
+        checked_at TEXT NOT NULL
+    );
+    CREATE INDEX reports_by_repo ON reports (owner, repo, checked_at);
+    CREATE INDEX reports_by_commit ON reports (owner, repo, commit_sha);
+    CREATE TABLE report_checks (
+        report_id INTEGER NOT NULL REFERENCES reports (id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        category TEXT NOT NULL,
+        severity TEXT NOT NULL,
+        status TEXT NOT NULL,
+        points INTEGER NOT NULL,
+        max_points INTEGER NOT NULL,
+        PRIMARY KEY (report_id, name)
+    );",
+];
+
+/// Shortest abbreviated commit SHA a commit can be looked up by
+pub const MIN_SHA_PREFIX: usize = 7;
+
+/// Columns of `reports`, in the order `StoredReport::from_row` reads them
+const REPORT_COLUMNS: &str = "id, owner, repo, git_ref, commit_sha, policy, pack_version, score, \
+     max_score, percentage, required_passed, conclusion, checked_at";
+
+/// The history in use, if one was opened
+static HISTORY: OnceLock<History> = OnceLock::new();
+
+/// A report as stored in the history
+#[derive(Debug, Serialize)]
+pub struct StoredReport {
+    pub id: i64,
+    pub owner: String,
+    pub repo: String,
+    /// Branch or tag the commit was checked for
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_sha: Option<String>,
+    pub policy: String,
+    pub pack_version: String,
+    pub score: u32,
+    pub max_score: u32,
+    pub percentage: f32,
+    pub required_passed: bool,
+    /// Check-run conclusion of the report on its own
+    pub conclusion: String,
+    pub checked_at: DateTime<Utc>,
+    /// Status of each check, only filled in when looking up a commit
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<StoredCheck>,
+}
+
+/// A check of a stored report
+#[derive(Debug, Serialize)]
+pub struct StoredCheck {
+    pub name: String,
+    pub category: String,
+    pub severity: String,
+    pub status: String,
+    pub points: u8,
+    pub max_points: u8,
+}
+
+impl StoredReport {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            owner: row.get(1)?,
+            repo: row.get(2)?,
+            git_ref: row.get(3)?,
+            commit_sha: row.get(4)?,
+            policy: row.get(5)?,
+            pack_version: row.get(6)?,
+            score: row.get(7)?,
+            max_score: row.get(8)?,
+            percentage: row.get::<_, f64>(9)? as f32,
+            required_passed: row.get(10)?,
+            conclusion: row.get(11)?,
+            checked_at: row.get(12)?,
+            checks: Vec::new(),
+        })
+    }
+
+    /// Checks of the report, to compare a later report against
+    ///
+    /// Checks whose stored status or severity is not understood are left out.
+    pub fn base_checks(&self) -> Vec<BaseCheck> {
+        self.checks
+            .iter()
+            .filter_map(|check| {
+                Some(BaseCheck {
+                    name: check.name.clone(),
+                    status: parse_name(&check.status)?,
+                    severity: parse_name(&check.severity)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Handle to the history database
+pub struct History {
+    conn: Arc<Mutex<Connection>>,
+}
+
+/// Open the history database at `path`, migrating it, and make it current
+pub fn open(path: &Path) -> Result<()> {
+    let mut conn = Connection::open(path)
+        .with_context(|| format!("Failed to open report history {}", path.display()))?;
+    conn.pragma_update(None, "foreign_keys", true)?;
+    migrate(&mut conn)
+        .with_context(|| format!("Failed to migrate report history {}", path.display()))?;
+
+    info!("Recording report history in {}", path.display());
+
+    let history = History {
+        conn: Arc::new(Mutex::new(conn)),
+    };
+    if HISTORY.set(history).is_err() {
+        anyhow::bail!("Report history is already open");
+    }
+
+    Ok(())
+}
+
+/// The history in use, `None` if none was opened
+pub fn current() -> Option<&'static History> {
+    HISTORY.get()
+}
+
+/// Bring the schema up to date
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        anyhow::bail!(
+            "Schema version {} is newer than this rhodibot supports ({})",
+            version,
+            MIGRATIONS.len()
+        );
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+        info!("Applied report history migration {}", index + 1);
+    }
+
+    Ok(())
+}
+
+/// Whether `sha` is a full or abbreviated commit SHA that can be looked up
+pub fn is_sha_prefix(sha: &str) -> bool {
+    (MIN_SHA_PREFIX..=64).contains(&sha.len()) && sha.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Name of an enum value as it is serialized in reports
+fn serialized_name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+/// Value serialized as `name`, the inverse of [`serialized_name`]
+fn parse_name<T: DeserializeOwned>(name: &str) -> Option<T> {
+    serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+}
+
+impl History {
+    /// Run a query off the async runtime
+    async fn with_conn<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().expect("report history poisoned");
+            f(&mut conn)
+        })
+        .await?
+    }
+
+    /// Store a report checked for `git_ref`
+    pub async fn record(&self, report: &ComplianceReport, git_ref: Option<&str>) -> Result<()> {
+        let stored = StoredReport {
+            id: 0,
+            owner: report.owner.clone(),
+            repo: report.repo.clone(),
+            git_ref: git_ref.map(str::to_string),
+            commit_sha: report.commit_sha.clone(),
+            policy: report.policy.to_string(),
+            pack_version: report.pack_version.clone(),
+            score: report.score,
+            max_score: report.max_score,
+            percentage: report.percentage,
+            required_passed: report.required_passed,
+            conclusion: report_conclusion(report).to_string(),
+            checked_at: Utc::now(),
+            checks: report
+                .checks
+                .iter()
+                .map(|check| StoredCheck {
+                    name: check.name.clone(),
+                    category: serialized_name(&check.category),
+                    severity: check.severity.to_string(),
+                    status: serialized_name(&check.status),
+                    points: check.points,
+                    max_points: check.max_points,
+                })
+                .collect(),
+        };
+
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT INTO reports (owner, repo, git_ref, commit_sha, policy, pack_version, \
+                 score, max_score, percentage, required_passed, conclusion, checked_at) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    stored.owner,
+                    stored.repo,
+                    stored.git_ref,
+                    stored.commit_sha,
+                    stored.policy,
+                    stored.pack_version,
+                    stored.score,
+                    stored.max_score,
+                    f64::from(stored.percentage),
+                    stored.required_passed,
+                    stored.conclusion,
+                    stored.checked_at,
+                ],
+            )?;
+            let id = tx.last_insert_rowid();
+
+            {
+                let mut insert = tx.prepare(
+                    "INSERT OR REPLACE INTO report_checks \
+                     (report_id, name, category, severity, status, points, max_points) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                )?;
+                for check in &stored.checks {
+                    insert.execute(params![
+                        id,
+                        check.name,
+                        check.category,
+                        check.severity,
+                        check.status,
+                        check.points,
+                        check.max_points,
+                    ])?;
+                }
+            }
+
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Reports of a repository, newest first, optionally only for one ref
+    pub async fn reports(
+        &self,
+        owner: &str,
+        repo: &str,
+        git_ref: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<StoredReport>> {
+        let (owner, repo) = (owner.to_string(), repo.to_string());
+        let git_ref = git_ref.map(str::to_string);
+
+        self.with_conn(move |conn| {
+            let mut query = conn.prepare(&format!(
+                "SELECT {} FROM reports \
+                 WHERE owner = ?1 AND repo = ?2 AND (?3 IS NULL OR git_ref = ?3) \
+                 ORDER BY checked_at DESC, id DESC LIMIT ?4",
+                REPORT_COLUMNS
+            ))?;
+            let reports = query
+                .query_map(
+                    params![owner, repo, git_ref, limit as i64],
+                    StoredReport::from_row,
+                )?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            Ok(reports)
+        })
+        .await
+    }
+
+    /// Reports of a commit with their checks, newest first
+    ///
+    /// `sha` may be abbreviated; see [`is_sha_prefix`].
+    pub async fn commit_reports(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+    ) -> Result<Vec<StoredReport>> {
+        if !is_sha_prefix(sha) {
+            anyhow::bail!("Not a commit SHA of at least {} characters: {}", MIN_SHA_PREFIX, sha);
+        }
+
+        let (owner, repo) = (owner.to_string(), repo.to_string());
+        // Lowercase hex sorts below `g`, so the prefix is a range on the index
+        let prefix = sha.to_ascii_lowercase();
+        let end = format!("{}g", prefix);
+
+        self.with_conn(move |conn| {
+            let mut query = conn.prepare(&format!(
+                "SELECT {} FROM reports \
+                 WHERE owner = ?1 AND repo = ?2 AND commit_sha >= ?3 AND commit_sha < ?4 \
+                 ORDER BY checked_at DESC, id DESC",
+                REPORT_COLUMNS
+            ))?;
+            let mut reports = query
+                .query_map(params![owner, repo, prefix, end], StoredReport::from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            let mut checks = conn.prepare(
+                "SELECT name, category, severity, status, points, max_points \
+                 FROM report_checks WHERE report_id = ?1 ORDER BY rowid",
+            )?;
+            for report in &mut reports {
+                report.checks = checks
+                    .query_map([report.id], |row| {
+                        Ok(StoredCheck {
+                            name: row.get(0)?,
+                            category: row.get(1)?,
+                            severity: row.get(2)?,
+                            status: row.get(3)?,
+                            points: row.get(4)?,
+                            max_points: row.get(5)?,
+                        })
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+            }
+
+            Ok(reports)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsr::tests::{check, report};
+    use crate::rsr::{CheckStatus, ReportDiff, Severity};
+
+    fn in_memory() -> History {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+
+        History {
+            conn: Arc::new(Mutex::new(conn)),
+        }
+    }
+
+    #[test]
+    fn migrate_is_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        migrate(&mut conn).unwrap();
+
+        let version: usize = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+
+    #[test]
+    fn migrate_rejects_newer_schemas() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+
+        assert!(migrate(&mut conn).is_err());
+    }
+
+    #[test]
+    fn sha_prefixes() {
+        let cases = [
+            ("0123456789abcdef0123456789abcdef01234567", true),
+            ("0123456", true),
+            ("ABCDEF0", true),
+            ("012345", false),
+            ("", false),
+            ("012345%", false),
+            ("0123456_", false),
+            ("g123456", false),
+        ];
+
+        for (sha, expected) in cases {
+            assert_eq!(is_sha_prefix(sha), expected, "{}", sha);
+        }
+    }
+
+    #[tokio::test]
+    async fn commit_reports_match_sha_prefixes() {
+        let history = in_memory();
+        for sha in ["abc1234aaaa", "abc1234bbbb", "abc1235cccc"] {
+            let mut report = report(Vec::new());
+            report.commit_sha = Some(sha.to_string());
+            history.record(&report, Some("main")).await.unwrap();
+        }
+
+        let cases = [
+            ("abc1234", 2),
+            ("ABC1234", 2),
+            ("abc1234aaaa", 1),
+            ("abc1236", 0),
+        ];
+        for (sha, expected) in cases {
+            let reports = history.commit_reports("owner", "repo", sha).await.unwrap();
+            assert_eq!(reports.len(), expected, "{}", sha);
+        }
+
+        assert!(history.commit_reports("owner", "repo", "abc%").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn stored_checks_diff_like_the_report() {
+        let history = in_memory();
+        let mut base = report(vec![
+            check("README.md", Severity::Required, CheckStatus::Pass),
+            check("SECURITY.md", Severity::Required, CheckStatus::Pass),
+            check("CHANGELOG.md", Severity::Recommended, CheckStatus::Fail),
+            check("CONTRIBUTING.md", Severity::Optional, CheckStatus::Fail),
+        ]);
+        let head = report(vec![
+            check("README.md", Severity::Required, CheckStatus::Fail),
+            check("CHANGELOG.md", Severity::Recommended, CheckStatus::Pass),
+            check("CONTRIBUTING.md", Severity::Optional, CheckStatus::Fail),
+        ]);
+        base.commit_sha = Some("abc1234".to_string());
+        history.record(&base, None).await.unwrap();
+
+        let stored = history.commit_reports("owner", "repo", "abc1234").await;
+        let stored = &stored.unwrap()[0];
+        let expected = ReportDiff::between(&base, &head);
+        let diff = ReportDiff::from_base(stored.percentage, &stored.base_checks(), &head);
+
+        assert_eq!(diff.new_failures, expected.new_failures);
+        assert_eq!(diff.removed_required, expected.removed_required);
+        assert_eq!(diff.existing_failures, expected.existing_failures);
+        assert_eq!(diff.fixed, expected.fixed);
+        assert_eq!(diff.base_percentage, expected.base_percentage);
+    }
+}
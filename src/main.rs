@@ -8,75 +8,307 @@
 use anyhow::Result;
 use axum::{
     extract::State,
-    http::StatusCode,
+    http::{StatusCode, header},
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
-use clap::Parser;
-use serde::Serialize;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+use std::process::ExitCode;
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tower_http::trace::TraceLayer;
 use tracing::{info, warn};
 
+mod auth;
+mod badge;
+mod checklist;
+mod checks;
+mod comment;
+mod commands;
 mod config;
+mod deliveries;
 mod github;
+mod history;
+mod metrics;
+mod policy;
+mod queue;
+mod render;
 mod rsr;
+mod sarif;
+mod scaffold;
+mod source;
+mod templates;
 mod webhook;
 
 use config::Config;
 
 /// RSR Compliance Bot for repository management
+///
+/// Without a subcommand rhodibot runs the webhook server, so the `serve`
+/// options may be given on their own.
 #[derive(Parser, Debug)]
 #[command(name = "rhodibot")]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// Port to listen on
-    #[arg(short, long, env = "PORT", default_value = "3000")]
-    port: u16,
-
     /// GitHub App ID
-    #[arg(long, env = "GITHUB_APP_ID")]
+    #[arg(long, env = "GITHUB_APP_ID", global = true)]
     app_id: Option<u64>,
 
     /// Path to GitHub App private key
-    #[arg(long, env = "GITHUB_PRIVATE_KEY_PATH")]
+    #[arg(long, env = "GITHUB_PRIVATE_KEY_PATH", global = true)]
     private_key_path: Option<String>,
 
+    /// Directory of policy pack documents (reloaded on SIGHUP)
+    #[arg(long, env = "RSR_POLICY_DIR", global = true)]
+    policy_dir: Option<std::path::PathBuf>,
+
+    /// Directory of templates overriding the embedded ones
+    #[arg(long, env = "RSR_TEMPLATE_DIR", global = true)]
+    template_dir: Option<std::path::PathBuf>,
+
+    #[command(flatten)]
+    serve: ServeArgs,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Options of the webhook server
+#[derive(Args, Debug)]
+struct ServeArgs {
+    /// Port to listen on
+    #[arg(short, long, env = "PORT", default_value = "3000")]
+    port: u16,
+
     /// Webhook secret for verification
     #[arg(long, env = "GITHUB_WEBHOOK_SECRET")]
     webhook_secret: Option<String>,
+
+    /// Number of workers processing webhook jobs
+    #[arg(long, env = "RSR_WORKERS", default_value = "4")]
+    workers: usize,
+
+    /// Webhook jobs that may wait before deliveries are rejected
+    #[arg(long, env = "RSR_QUEUE_CAPACITY", default_value = "1000")]
+    queue_capacity: usize,
+
+    /// Attempts per webhook job before it is given up
+    #[arg(long, env = "RSR_JOB_ATTEMPTS", default_value = "5")]
+    job_attempts: u32,
+
+    /// File remembering processed webhook deliveries across restarts
+    #[arg(long, env = "RSR_DELIVERY_LOG")]
+    delivery_log: Option<std::path::PathBuf>,
+
+    /// SQLite database recording the report of every check run
+    #[arg(long, env = "RSR_HISTORY_DB")]
+    history_db: Option<std::path::PathBuf>,
+
+    /// Seconds a repository's badge is served from cache before it is checked again
+    #[arg(long, env = "RSR_BADGE_TTL", default_value = "300")]
+    badge_ttl: u64,
+
+    /// Reject events whose timestamp is further than this many seconds from now (0 to accept any)
+    #[arg(long, env = "RSR_MAX_EVENT_AGE", default_value = "3600")]
+    max_event_age: u64,
+}
+
+/// Rhodibot subcommands
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the webhook server (the default)
+    Serve(ServeArgs),
+    /// Check a repository and print its compliance report
+    ///
+    /// Exits non-zero when required checks fail.
+    Check {
+        /// `owner/repo` on GitHub, or the path to a local checkout or bare repository
+        target: String,
+        /// Branch, tag or SHA to check (defaults to HEAD locally, the default branch on GitHub)
+        #[arg(long)]
+        rev: Option<String>,
+        /// Output format
+        #[arg(long, value_enum, default_value = "markdown")]
+        format: render::ReportFormat,
+        /// Print the report as JSON (same as `--format json`)
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+    },
+    /// Check every repository in an organization
+    ScanOrg {
+        /// Organization login
+        org: String,
+    },
+    /// Explain a check: what it verifies, its severity per policy pack and how to fix it
+    Explain {
+        /// Check name as shown in reports (e.g. `SECURITY.md`, `no-go.mod`)
+        check: String,
+    },
 }
 
 /// Application state shared across handlers
 #[derive(Clone)]
 struct AppState {
     config: Arc<Config>,
+    queue: queue::JobQueue,
+    deliveries: Arc<deliveries::DeliveryStore>,
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize logging
+async fn main() -> Result<ExitCode> {
+    // Initialize logging (on stderr, so command output stays clean)
     tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "rhodibot=info,tower_http=info".into()),
         )
+        .with_writer(std::io::stderr)
         .init();
 
     // Load environment variables
     dotenvy::dotenv().ok();
 
     // Parse CLI arguments
-    let cli = Cli::parse();
+    let cli = parse_cli();
+
+    // Build configuration, with the server options of `serve` if given
+    let serve_args = match &cli.command {
+        Some(Command::Serve(args)) => args,
+        _ => &cli.serve,
+    };
+    let config = Config::from_cli(&cli, serve_args)?;
+    let port = serve_args.port;
+
+    // Load and validate policy packs and templates before doing any work
+    policy::load(config.policy_dir.as_deref())?;
+    templates::load(config.template_dir.as_deref())?;
+
+    match cli.command {
+        None | Some(Command::Serve(_)) => serve(config, port).await,
+        Some(Command::Check {
+            target,
+            rev,
+            format,
+            json,
+        }) => {
+            let format = if json {
+                render::ReportFormat::Json
+            } else {
+                format
+            };
+            commands::check(&config, &target, rev.as_deref(), format).await
+        }
+        Some(Command::ScanOrg { org }) => commands::scan_org(&config, &org).await,
+        Some(Command::Explain { check }) => commands::explain(&check),
+    }
+}
+
+/// Parse the command line, exiting with usage on errors
+fn parse_cli() -> Cli {
+    try_parse_cli(std::env::args_os()).unwrap_or_else(|e| e.exit())
+}
+
+/// Parse arguments, rejecting server options given to other commands
+///
+/// The server options are accepted before any subcommand so that `serve` can
+/// be left out, which clap cannot tell apart from `rhodibot --port 80 check`.
+fn try_parse_cli<I, T>(args: I) -> Result<Cli, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<std::ffi::OsString> + Clone,
+{
+    let matches = Cli::command().try_get_matches_from(args)?;
+    let cli = Cli::from_arg_matches(&matches)?;
+
+    if let Some((name, _)) = matches.subcommand() {
+        let serve = ServeArgs::augment_args(clap::Command::new("serve"));
+        let given = serve.get_arguments().find(|arg| {
+            matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+        });
+
+        if let Some(arg) = given {
+            let flag = arg.get_long().unwrap_or(arg.get_id().as_str());
+            let message = if name == "serve" {
+                format!("--{} must follow `serve`", flag)
+            } else {
+                format!("--{} is an option of `serve`, not `{}`", flag, name)
+            };
+            return Err(Cli::command().error(ErrorKind::ArgumentConflict, message));
+        }
+    }
 
+    Ok(cli)
+}
+
+/// How long queued webhook jobs may take to drain on shutdown
+const SHUTDOWN_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Resolve on Ctrl-C or, on Unix, SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+
+    info!("Shutdown requested, no longer accepting webhooks");
+}
+
+/// Run the webhook server
+async fn serve(config: Config, port: u16) -> Result<ExitCode> {
     info!("Starting Rhodibot v{}", env!("CARGO_PKG_VERSION"));
 
-    // Build configuration
-    let config = Config::from_cli(&cli)?;
+    #[cfg(unix)]
+    if let Some(dir) = config.policy_dir.clone() {
+        tokio::spawn(async move {
+            if let Err(e) = policy::reload_on_sighup(dir).await {
+                warn!("Policy reload on SIGHUP unavailable: {}", e);
+            }
+        });
+    }
+
+    if let Some(path) = config.history_db.as_deref() {
+        history::open(path)?;
+    }
+
+    let deliveries = Arc::new(deliveries::DeliveryStore::open(
+        deliveries::DEFAULT_CAPACITY,
+        config.delivery_log.as_deref(),
+    )?);
+    let config = Arc::new(config);
+    let queue = queue::JobQueue::start(config.clone(), deliveries.clone());
+
     let state = AppState {
-        config: Arc::new(config),
+        config,
+        queue: queue.clone(),
+        deliveries,
     };
 
     // Build router
@@ -84,18 +316,28 @@ async fn main() -> Result<()> {
         .route("/", get(health_check))
         .route("/health", get(health_check))
         .route("/webhook", post(webhook_handler))
+        .route("/metrics", get(metrics_handler))
         .route("/api/check/{owner}/{repo}", get(check_repository))
+        .route("/api/badge/{owner}/{repo}", get(repository_badge))
+        .route("/api/history/{owner}/{repo}", get(repository_history))
+        .route("/api/history/{owner}/{repo}/{sha}", get(commit_history))
         .layer(TraceLayer::new_for_http())
         .with_state(state);
 
     // Start server
-    let addr = format!("0.0.0.0:{}", cli.port);
+    let addr = format!("0.0.0.0:{}", port);
     let listener = TcpListener::bind(&addr).await?;
     info!("Listening on {}", addr);
 
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    // Finish the work already acknowledged to GitHub
+    queue.shutdown(SHUTDOWN_DRAIN_TIMEOUT).await;
+    info!("Shut down");
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
 
 /// Health check endpoint
@@ -114,6 +356,14 @@ struct HealthResponse {
     name: String,
 }
 
+/// Prometheus metrics
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        metrics::render(state.queue.depth()),
+    )
+}
+
 /// Webhook handler for GitHub events
 async fn webhook_handler(
     State(state): State<AppState>,
@@ -125,10 +375,12 @@ async fn webhook_handler(
         if let Some(signature) = headers.get("x-hub-signature-256") {
             if !webhook::verify_signature(secret, &body, signature.to_str().unwrap_or("")) {
                 warn!("Invalid webhook signature");
+                metrics::signature_failure();
                 return (StatusCode::UNAUTHORIZED, "Invalid signature").into_response();
             }
         } else {
             warn!("Missing webhook signature");
+            metrics::signature_failure();
             return (StatusCode::UNAUTHORIZED, "Missing signature").into_response();
         }
     }
@@ -138,45 +390,326 @@ async fn webhook_handler(
         .get("x-github-event")
         .and_then(|v| v.to_str().ok())
         .unwrap_or("unknown");
+    let delivery = headers
+        .get("x-github-delivery")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    info!(
+        "Received webhook event: {} (delivery {})",
+        event_type,
+        delivery.as_deref().unwrap_or("-")
+    );
+
+    if event_type == "ping" {
+        info!("Received ping event");
+        metrics::webhook_delivery(event_type, "ping");
+        return (StatusCode::OK, "OK").into_response();
+    }
+
+    let payload: serde_json::Value = match serde_json::from_str(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            warn!("Invalid webhook payload: {}", e);
+            metrics::webhook_delivery(event_type, "invalid_payload");
+            return (StatusCode::BAD_REQUEST, "Invalid payload").into_response();
+        }
+    };
+
+    // Reject replays of old events
+    if let Some(max_age) = state.config.max_event_age
+        && let Some(timestamp) = webhook::event_timestamp(event_type, &payload)
+        && chrono::Utc::now()
+            .signed_duration_since(timestamp)
+            .num_seconds()
+            .unsigned_abs()
+            > max_age.as_secs()
+    {
+        warn!(
+            "Rejecting {} event from {} (outside the {}s window)",
+            event_type,
+            timestamp,
+            max_age.as_secs()
+        );
+        metrics::webhook_delivery(event_type, "stale");
+        return (StatusCode::BAD_REQUEST, "Event timestamp outside window").into_response();
+    }
 
-    info!("Received webhook event: {}", event_type);
+    // Acknowledge redeliveries without processing them again
+    if let Some(id) = &delivery
+        && !state.deliveries.insert(id)
+    {
+        info!("Delivery {} already processed, skipping", id);
+        metrics::webhook_delivery(event_type, "duplicate");
+        return (StatusCode::OK, "Already processed").into_response();
+    }
+
+    // Acknowledge now; the check runs on a worker
+    let delivery_id = delivery.clone();
+    let job = queue::Job {
+        event: event_type.to_string(),
+        delivery,
+        body,
+    };
 
-    // Process event
-    match event_type {
-        "push" => webhook::handle_push(&state.config, &body).await,
-        "pull_request" => webhook::handle_pull_request(&state.config, &body).await,
-        "repository" => webhook::handle_repository(&state.config, &body).await,
-        "installation" | "installation_repositories" => {
-            webhook::handle_installation(&state.config, &body).await
+    match state.queue.enqueue(job, &payload) {
+        Ok(queue::Enqueued::Queued) => {
+            metrics::webhook_delivery(event_type, "queued");
+            (StatusCode::ACCEPTED, "Queued").into_response()
         }
-        "ping" => {
-            info!("Received ping event");
-            Ok(())
+        Ok(queue::Enqueued::Coalesced) => {
+            info!("Coalesced with a queued {} job", event_type);
+            metrics::webhook_delivery(event_type, "coalesced");
+            (StatusCode::ACCEPTED, "Coalesced").into_response()
         }
-        _ => {
-            info!("Ignoring event type: {}", event_type);
-            Ok(())
+        Err(e) => {
+            // Not taken on, so a redelivery must be processed
+            if let Some(id) = &delivery_id {
+                state.deliveries.remove(id);
+            }
+            warn!("Rejecting webhook: {}", e);
+            metrics::webhook_delivery(event_type, "rejected");
+            (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response()
         }
     }
-    .map(|_| (StatusCode::OK, "OK").into_response())
-    .unwrap_or_else(|e| {
-        warn!("Error processing webhook: {}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Error").into_response()
-    })
 }
 
-/// Check a repository for RSR compliance
+/// Query parameters of the check endpoint
+#[derive(Debug, Deserialize)]
+struct CheckQuery {
+    /// Branch, tag or SHA to check (defaults to the default branch)
+    #[serde(rename = "ref")]
+    git_ref: Option<String>,
+    /// Output format, overriding the `Accept` header
+    format: Option<render::ReportFormat>,
+}
+
+/// Check a public repository for RSR compliance
+///
+/// The report is JSON unless `?format=` or the `Accept` header asks for
+/// another format.
 async fn check_repository(
     State(state): State<AppState>,
     axum::extract::Path((owner, repo)): axum::extract::Path<(String, String)>,
+    axum::extract::Query(query): axum::extract::Query<CheckQuery>,
+    headers: axum::http::HeaderMap,
 ) -> impl IntoResponse {
     info!("Checking repository: {}/{}", owner, repo);
 
-    match rsr::check_compliance(&state.config, &owner, &repo).await {
-        Ok(report) => Json(report).into_response(),
+    let format = query
+        .format
+        .or_else(|| {
+            headers
+                .get(header::ACCEPT)
+                .and_then(|accept| accept.to_str().ok())
+                .and_then(render::ReportFormat::from_accept)
+        })
+        .unwrap_or(render::ReportFormat::Json);
+
+    let Some(client) = public_repo_client(&state.config, &owner, &repo).await else {
+        return (StatusCode::NOT_FOUND, "No public repository with this name").into_response();
+    };
+
+    let source = source::GitHubSource::new(&client, &owner, &repo, query.git_ref.as_deref());
+
+    match rsr::check_compliance(&source).await {
+        Ok(report) => match render::render(&report, format) {
+            Ok(body) => ([(header::CONTENT_TYPE, format.content_type())], body).into_response(),
+            Err(e) => {
+                warn!("Error rendering report: {:#}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            }
+        },
         Err(e) => {
             warn!("Error checking repository: {}", e);
             (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
         }
     }
 }
+
+/// Query parameters of the badge endpoint
+#[derive(Debug, Deserialize)]
+struct BadgeQuery {
+    /// `shields` for shields.io endpoint JSON instead of SVG
+    format: Option<String>,
+}
+
+/// Compliance badge of a repository's default branch
+async fn repository_badge(
+    State(state): State<AppState>,
+    axum::extract::Path((owner, repo)): axum::extract::Path<(String, String)>,
+    axum::extract::Query(query): axum::extract::Query<BadgeQuery>,
+) -> impl IntoResponse {
+    let Some((badge, ttl)) = badge::get(&state.config, &owner, &repo).await else {
+        return (StatusCode::NOT_FOUND, "No badge for this repository").into_response();
+    };
+    let cache_control = format!("max-age={}", ttl.as_secs());
+
+    match query.format.as_deref() {
+        Some("shields") => (
+            [(header::CACHE_CONTROL, cache_control)],
+            Json(badge.shields(ttl)),
+        )
+            .into_response(),
+        None | Some("svg") => (
+            [
+                (header::CONTENT_TYPE, "image/svg+xml".to_string()),
+                (header::CACHE_CONTROL, cache_control),
+            ],
+            badge.svg(),
+        )
+            .into_response(),
+        Some(other) => (
+            StatusCode::BAD_REQUEST,
+            format!("Unknown badge format {:?}", other),
+        )
+            .into_response(),
+    }
+}
+
+/// Reports listed by the history endpoint when no limit is given
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// Most reports the history endpoint lists at once
+const MAX_HISTORY_LIMIT: usize = 1000;
+
+/// Query parameters of the history endpoint
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    /// Only list reports checked for this ref
+    #[serde(rename = "ref")]
+    git_ref: Option<String>,
+    /// Number of reports to list, newest first
+    limit: Option<usize>,
+}
+
+/// Client for a public repository, whose reports may be served to anyone
+///
+/// `None` if the repository is private or cannot be looked up. Repositories
+/// the App is not installed on are not looked up at all.
+async fn public_repo_client(
+    config: &Config,
+    owner: &str,
+    repo: &str,
+) -> Option<github::GitHubClient> {
+    let lookup = async {
+        let Some(client) = github::GitHubClient::for_installed_repo(config, owner, repo).await?
+        else {
+            return Ok(None);
+        };
+        let public = !client.get_repository(owner, repo).await?.private;
+        anyhow::Ok(public.then_some(client))
+    };
+
+    lookup.await.unwrap_or_else(|e| {
+        warn!("Failed to look up {}/{}: {:#}", owner, repo, e);
+        None
+    })
+}
+
+/// Whether a repository is public, so its reports may be served to anyone
+async fn is_public(config: &Config, owner: &str, repo: &str) -> bool {
+    public_repo_client(config, owner, repo).await.is_some()
+}
+
+/// List the stored reports of a public repository
+async fn repository_history(
+    State(state): State<AppState>,
+    axum::extract::Path((owner, repo)): axum::extract::Path<(String, String)>,
+    axum::extract::Query(query): axum::extract::Query<HistoryQuery>,
+) -> impl IntoResponse {
+    let Some(history) = history::current() else {
+        return (StatusCode::NOT_FOUND, "Report history is not enabled").into_response();
+    };
+    if !is_public(&state.config, &owner, &repo).await {
+        return (StatusCode::NOT_FOUND, "No reports for this repository").into_response();
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .min(MAX_HISTORY_LIMIT);
+
+    match history
+        .reports(&owner, &repo, query.git_ref.as_deref(), limit)
+        .await
+    {
+        Ok(reports) => Json(reports).into_response(),
+        Err(e) => {
+            warn!("Error reading report history: {:#}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// Show the stored reports of a commit of a public repository with their checks
+async fn commit_history(
+    State(state): State<AppState>,
+    axum::extract::Path((owner, repo, sha)): axum::extract::Path<(String, String, String)>,
+) -> impl IntoResponse {
+    let Some(history) = history::current() else {
+        return (StatusCode::NOT_FOUND, "Report history is not enabled").into_response();
+    };
+    if !history::is_sha_prefix(&sha) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Expected a commit SHA of at least {} hex characters",
+                history::MIN_SHA_PREFIX
+            ),
+        )
+            .into_response();
+    }
+    if !is_public(&state.config, &owner, &repo).await {
+        return (StatusCode::NOT_FOUND, "No reports for this repository").into_response();
+    }
+
+    match history.commit_reports(&owner, &repo, &sha).await {
+        Ok(reports) if reports.is_empty() => {
+            (StatusCode::NOT_FOUND, "No reports for this commit").into_response()
+        }
+        Ok(reports) => Json(reports).into_response(),
+        Err(e) => {
+            warn!("Error reading report history: {:#}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_options_belong_to_serve() {
+        Cli::command().debug_assert();
+
+        let port = |args: &[&str]| {
+            let cli = try_parse_cli(args.iter().copied()).map_err(|e| e.kind())?;
+            Ok(match cli.command {
+                Some(Command::Serve(args)) => args.port,
+                _ => cli.serve.port,
+            })
+        };
+
+        assert_eq!(port(&["rhodibot", "--port", "80"]), Ok(80));
+        assert_eq!(port(&["rhodibot", "serve", "--port", "80"]), Ok(80));
+        assert_eq!(port(&["rhodibot", "check", ".", "--app-id", "1"]), Ok(3000));
+        assert_eq!(
+            port(&["rhodibot", "--app-id", "1", "explain", "SECURITY.md"]),
+            Ok(3000)
+        );
+        assert_eq!(
+            port(&["rhodibot", "--port", "80", "serve"]),
+            Err(ErrorKind::ArgumentConflict)
+        );
+        assert_eq!(
+            port(&["rhodibot", "--workers", "2", "check", "."]),
+            Err(ErrorKind::ArgumentConflict)
+        );
+        assert_eq!(
+            port(&["rhodibot", "check", ".", "--port", "80"]),
+            Err(ErrorKind::UnknownArgument)
+        );
+    }
+}
@@ -0,0 +1,369 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! Prometheus metrics
+//!
+//! Counters, gauges and the check-duration histogram are kept in memory and
+//! written in the Prometheus text exposition format by `/metrics`. Label
+//! values are bounded: GitHub endpoints are recorded as path templates and
+//! compliance gauges keep one series per repository.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use crate::rsr::ComplianceReport;
+
+/// Upper bounds of the check-duration histogram buckets, in seconds
+const CHECK_DURATION_BUCKETS: [f64; 10] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+/// Events counted under their own name; others are counted as `other`
+const EVENTS: &[&str] = &[
+    "ping",
+    "push",
+    "pull_request",
+    "check_suite",
+    "check_run",
+    "repository",
+    "installation",
+    "installation_repositories",
+];
+
+static METRICS: LazyLock<Mutex<Metrics>> = LazyLock::new(|| Mutex::new(Metrics::default()));
+
+#[derive(Default)]
+struct Metrics {
+    /// By event and outcome
+    webhook_deliveries: BTreeMap<(String, &'static str), u64>,
+    /// By event and outcome
+    webhook_jobs: BTreeMap<(String, &'static str), u64>,
+    signature_failures: u64,
+    check_duration: Histogram,
+    /// By endpoint and status
+    github_requests: BTreeMap<(String, String), u64>,
+    /// By rate-limit resource
+    rate_limit_remaining: BTreeMap<String, u64>,
+    /// Policy pack and percentage by `owner/repo`
+    compliance: BTreeMap<String, (String, f32)>,
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// Observations per bucket, not cumulative
+    buckets: [u64; CHECK_DURATION_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if let Some(bucket) = CHECK_DURATION_BUCKETS.iter().position(|le| value <= *le) {
+            self.buckets[bucket] += 1;
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+/// Event label of an `X-GitHub-Event` header value
+fn event_label(event: &str) -> String {
+    if EVENTS.contains(&event) {
+        event.to_string()
+    } else {
+        "other".to_string()
+    }
+}
+
+fn with<F: FnOnce(&mut Metrics)>(f: F) {
+    f(&mut METRICS.lock().expect("metrics poisoned"));
+}
+
+/// Count a webhook delivery by what the server answered
+pub fn webhook_delivery(event: &str, outcome: &'static str) {
+    with(|m| *m.webhook_deliveries.entry((event_label(event), outcome)).or_default() += 1);
+}
+
+/// Count a webhook job by whether it eventually succeeded
+pub fn webhook_job(event: &str, succeeded: bool) {
+    let outcome = if succeeded { "succeeded" } else { "failed" };
+    with(|m| *m.webhook_jobs.entry((event_label(event), outcome)).or_default() += 1);
+}
+
+/// Count a webhook with a missing or invalid signature
+pub fn signature_failure() {
+    with(|m| m.signature_failures += 1);
+}
+
+/// Record how long evaluating a compliance report took
+pub fn check_duration(duration: Duration) {
+    with(|m| m.check_duration.observe(duration.as_secs_f64()));
+}
+
+/// Count a GitHub API request by endpoint template and status
+///
+/// `status` is the HTTP status code, or `error` if no response arrived.
+pub fn github_request(endpoint: String, status: String) {
+    with(|m| *m.github_requests.entry((endpoint, status)).or_default() += 1);
+}
+
+/// Record the rate limit left for a GitHub API resource (`core`, `search`, ...)
+pub fn rate_limit_remaining(resource: &str, remaining: u64) {
+    with(|m| {
+        m.rate_limit_remaining
+            .insert(resource.to_string(), remaining);
+    });
+}
+
+/// Record the latest default-branch compliance of a repository
+pub fn compliance(report: &ComplianceReport) {
+    let repo = format!("{}/{}", report.owner, report.repo);
+    let entry = (report.policy.to_string(), report.percentage);
+    with(|m| {
+        m.compliance.insert(repo, entry);
+    });
+}
+
+/// Escape a label value
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// All metrics in the Prometheus text format
+pub fn render(queue_depth: usize) -> String {
+    exposition(&METRICS.lock().expect("metrics poisoned"), queue_depth)
+}
+
+/// Metrics in the Prometheus text format
+fn exposition(m: &Metrics, queue_depth: usize) -> String {
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "rhodibot_webhook_deliveries_total",
+        "counter",
+        "Webhook deliveries by event and how they were answered.",
+    );
+    for ((event, outcome), count) in &m.webhook_deliveries {
+        let _ = writeln!(
+            out,
+            "rhodibot_webhook_deliveries_total{{event=\"{}\",outcome=\"{}\"}} {}",
+            escape(event),
+            outcome,
+            count
+        );
+    }
+
+    header(
+        &mut out,
+        "rhodibot_webhook_jobs_total",
+        "counter",
+        "Queued webhook jobs by event and whether they eventually succeeded.",
+    );
+    for ((event, outcome), count) in &m.webhook_jobs {
+        let _ = writeln!(
+            out,
+            "rhodibot_webhook_jobs_total{{event=\"{}\",outcome=\"{}\"}} {}",
+            escape(event),
+            outcome,
+            count
+        );
+    }
+
+    header(
+        &mut out,
+        "rhodibot_webhook_signature_failures_total",
+        "counter",
+        "Webhooks rejected for a missing or invalid signature.",
+    );
+    let _ = writeln!(
+        out,
+        "rhodibot_webhook_signature_failures_total {}",
+        m.signature_failures
+    );
+
+    header(
+        &mut out,
+        "rhodibot_job_queue_depth",
+        "gauge",
+        "Webhook jobs waiting to be processed.",
+    );
+    let _ = writeln!(out, "rhodibot_job_queue_depth {}", queue_depth);
+
+    header(
+        &mut out,
+        "rhodibot_check_duration_seconds",
+        "histogram",
+        "Time taken to evaluate a compliance report.",
+    );
+    let mut cumulative = 0;
+    for (le, count) in CHECK_DURATION_BUCKETS.iter().zip(m.check_duration.buckets) {
+        cumulative += count;
+        let _ = writeln!(
+            out,
+            "rhodibot_check_duration_seconds_bucket{{le=\"{}\"}} {}",
+            le, cumulative
+        );
+    }
+    let _ = writeln!(
+        out,
+        "rhodibot_check_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+        m.check_duration.count
+    );
+    let _ = writeln!(
+        out,
+        "rhodibot_check_duration_seconds_sum {}",
+        m.check_duration.sum
+    );
+    let _ = writeln!(
+        out,
+        "rhodibot_check_duration_seconds_count {}",
+        m.check_duration.count
+    );
+
+    header(
+        &mut out,
+        "rhodibot_github_requests_total",
+        "counter",
+        "GitHub API requests by endpoint and response status.",
+    );
+    for ((endpoint, status), count) in &m.github_requests {
+        let _ = writeln!(
+            out,
+            "rhodibot_github_requests_total{{endpoint=\"{}\",status=\"{}\"}} {}",
+            escape(endpoint),
+            escape(status),
+            count
+        );
+    }
+
+    header(
+        &mut out,
+        "rhodibot_github_rate_limit_remaining",
+        "gauge",
+        "GitHub API requests left in the current rate-limit window, as of the last response.",
+    );
+    for (resource, remaining) in &m.rate_limit_remaining {
+        let _ = writeln!(
+            out,
+            "rhodibot_github_rate_limit_remaining{{resource=\"{}\"}} {}",
+            escape(resource),
+            remaining
+        );
+    }
+
+    header(
+        &mut out,
+        "rhodibot_compliance_percentage",
+        "gauge",
+        "Latest RSR compliance percentage of a repository's default branch.",
+    );
+    for (repo, (policy, percentage)) in &m.compliance {
+        let _ = writeln!(
+            out,
+            "rhodibot_compliance_percentage{{repo=\"{}\",policy=\"{}\"}} {}",
+            escape(repo),
+            escape(policy),
+            percentage
+        );
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_inclusive_upper_bounds() {
+        let cases = [
+            (0.05, Some(0)),
+            (0.1, Some(0)),
+            (0.11, Some(1)),
+            (1.0, Some(3)),
+            (120.0, Some(9)),
+            (120.5, None),
+        ];
+
+        for (value, bucket) in cases {
+            let mut histogram = Histogram::default();
+            histogram.observe(value);
+
+            let mut expected = [0; CHECK_DURATION_BUCKETS.len()];
+            if let Some(bucket) = bucket {
+                expected[bucket] = 1;
+            }
+            assert_eq!(histogram.buckets, expected, "{}", value);
+            assert_eq!(histogram.count, 1, "{}", value);
+        }
+    }
+
+    #[test]
+    fn exposition_format() {
+        let mut m = Metrics::default();
+        m.webhook_deliveries
+            .insert(("push".to_string(), "accepted"), 3);
+        m.github_requests
+            .insert(("/repos/{owner}/{repo}".to_string(), "200".to_string()), 2);
+        m.compliance.insert(
+            "acme/\"quoted\"".to_string(),
+            ("standard".to_string(), 87.5),
+        );
+        for seconds in [0.2, 0.3, 200.0] {
+            m.check_duration.observe(seconds);
+        }
+
+        let out = exposition(&m, 4);
+        let lines: Vec<&str> = out.lines().collect();
+
+        for expected in [
+            "# HELP rhodibot_job_queue_depth Webhook jobs waiting to be processed.",
+            "# TYPE rhodibot_job_queue_depth gauge",
+            "rhodibot_job_queue_depth 4",
+            "rhodibot_webhook_deliveries_total{event=\"push\",outcome=\"accepted\"} 3",
+            "rhodibot_webhook_signature_failures_total 0",
+            "rhodibot_github_requests_total{endpoint=\"/repos/{owner}/{repo}\",status=\"200\"} 2",
+            "rhodibot_compliance_percentage{repo=\"acme/\\\"quoted\\\"\",policy=\"standard\"} 87.5",
+            "# TYPE rhodibot_check_duration_seconds histogram",
+            "rhodibot_check_duration_seconds_bucket{le=\"0.1\"} 0",
+            "rhodibot_check_duration_seconds_bucket{le=\"0.25\"} 1",
+            "rhodibot_check_duration_seconds_bucket{le=\"0.5\"} 2",
+            "rhodibot_check_duration_seconds_bucket{le=\"120\"} 2",
+            "rhodibot_check_duration_seconds_bucket{le=\"+Inf\"} 3",
+            "rhodibot_check_duration_seconds_sum 200.5",
+            "rhodibot_check_duration_seconds_count 3",
+        ] {
+            assert!(
+                lines.contains(&expected),
+                "missing {:?} in:\n{}",
+                expected,
+                out
+            );
+        }
+
+        // Every sample follows its family's HELP and TYPE lines
+        for line in lines.iter().filter(|line| !line.starts_with('#')) {
+            let name = line.split(['{', ' ']).next().unwrap();
+            let family = ["_bucket", "_sum", "_count"]
+                .iter()
+                .find_map(|suffix| {
+                    name.strip_suffix(suffix)
+                        .filter(|family| family.ends_with("_seconds"))
+                })
+                .unwrap_or(name);
+            assert!(
+                out.contains(&format!("# TYPE {} ", family)),
+                "no TYPE for {}",
+                line
+            );
+        }
+    }
+}
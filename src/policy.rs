@@ -0,0 +1,324 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! Policy pack documents
+//!
+//! Each built-in policy pack (minimal, standard, strict, enterprise) is a
+//! versioned TOML or JSON document listing the required files, banned
+//! patterns and approved licenses with their severities. The packs shipped in
+//! `policies/` are embedded in the binary; a policy directory given at startup
+//! replaces them without a rebuild, and the server reloads it on SIGHUP.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, RwLock};
+use tracing::{info, warn};
+
+use crate::rsr::{BUILTIN_PACKS, CheckCategory, PolicyPack, Severity};
+
+/// Document schema version understood by this build
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Packs shipped with rhodibot
+const EMBEDDED_PACKS: [(&str, &str); 4] = [
+    ("minimal.toml", include_str!("../policies/minimal.toml")),
+    ("standard.toml", include_str!("../policies/standard.toml")),
+    ("strict.toml", include_str!("../policies/strict.toml")),
+    ("enterprise.toml", include_str!("../policies/enterprise.toml")),
+];
+
+/// Policy packs in effect, swapped as a whole on reload
+static PACKS: LazyLock<RwLock<Arc<PackSet>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(PackSet::embedded())));
+
+/// A versioned policy pack document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackDocument {
+    /// Document schema version, must equal [`SCHEMA_VERSION`]
+    pub schema: u32,
+    /// Pack the document defines
+    pub pack: PolicyPack,
+    /// Pack version, recorded in every report evaluated against it
+    pub version: String,
+    /// One-line summary shown in reports
+    pub description: String,
+    pub license: LicenseRule,
+    #[serde(default)]
+    pub files: Vec<FileRule>,
+    #[serde(default)]
+    pub banned: Vec<BanRule>,
+}
+
+/// Approved-license rule of a pack
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LicenseRule {
+    pub severity: Severity,
+    pub points: u8,
+    /// Approved licenses (GitHub license keys)
+    pub approved: Vec<String>,
+}
+
+/// File or directory a pack requires
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileRule {
+    /// Path relative to the repository root
+    pub path: String,
+    pub description: String,
+    pub category: CheckCategory,
+    pub points: u8,
+    pub severity: Severity,
+    /// How to fix a failing check
+    pub remediation: String,
+}
+
+/// Gitignore-style glob a pack bans
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BanRule {
+    pub pattern: String,
+    pub description: String,
+    #[serde(default = "BanRule::default_category")]
+    pub category: CheckCategory,
+    pub severity: Severity,
+    /// How to fix a violation
+    pub remediation: String,
+}
+
+impl BanRule {
+    fn default_category() -> CheckCategory {
+        CheckCategory::LanguagePolicy
+    }
+}
+
+impl PackDocument {
+    /// Parse a document, as JSON if the file name ends in `.json`, else TOML
+    pub fn parse(file_name: &str, content: &str) -> Result<Self> {
+        let document: Self = if file_name.ends_with(".json") {
+            serde_json::from_str(content)?
+        } else {
+            toml::from_str(content)?
+        };
+
+        document.validate()?;
+
+        Ok(document)
+    }
+
+    /// Check the document against the rules serde cannot express
+    pub fn validate(&self) -> Result<()> {
+        if self.schema != SCHEMA_VERSION {
+            anyhow::bail!(
+                "unsupported schema version {} (expected {})",
+                self.schema,
+                SCHEMA_VERSION
+            );
+        }
+        if self.pack == PolicyPack::Custom {
+            anyhow::bail!("custom packs are defined in .rsr.toml, not policy documents");
+        }
+        if self.version.trim().is_empty() {
+            anyhow::bail!("version must not be empty");
+        }
+        if self.license.approved.is_empty() {
+            anyhow::bail!("license.approved must list at least one license");
+        }
+
+        let mut ids = HashSet::from(["license-type".to_string()]);
+
+        for file in &self.files {
+            if file.path.is_empty() || file.path.starts_with('/') {
+                anyhow::bail!("invalid file path {:?}: must be relative to the root", file.path);
+            }
+            if !ids.insert(file.path.clone()) {
+                anyhow::bail!("duplicate check {}", file.path);
+            }
+        }
+        for banned in &self.banned {
+            if gix::glob::parse(&banned.pattern).is_none() {
+                anyhow::bail!("invalid banned pattern {:?}", banned.pattern);
+            }
+            if !ids.insert(format!("no-{}", banned.pattern)) {
+                anyhow::bail!("duplicate banned pattern {}", banned.pattern);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// One document per built-in policy pack
+#[derive(Debug)]
+pub struct PackSet {
+    packs: Vec<PackDocument>,
+}
+
+impl PackSet {
+    /// Packs embedded in the binary
+    pub fn embedded() -> Self {
+        let packs = EMBEDDED_PACKS
+            .iter()
+            .map(|(name, content)| {
+                PackDocument::parse(name, content)
+                    .unwrap_or_else(|e| panic!("embedded policy pack {} is invalid: {}", name, e))
+            })
+            .collect();
+
+        Self { packs }
+    }
+
+    /// Load every `*.toml` and `*.json` document in a directory
+    ///
+    /// Packs the directory does not define keep their embedded version.
+    pub fn load_dir(dir: &Path) -> Result<Self> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read policy directory {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == "toml" || ext == "json")
+            })
+            .collect();
+        paths.sort();
+
+        let mut loaded: Vec<PackDocument> = Vec::new();
+
+        for path in paths {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let document = PackDocument::parse(&path.to_string_lossy(), &content)
+                .with_context(|| format!("Invalid policy pack {}", path.display()))?;
+
+            if loaded.iter().any(|d| d.pack == document.pack) {
+                anyhow::bail!(
+                    "Policy pack {} is defined more than once in {}",
+                    document.pack,
+                    dir.display()
+                );
+            }
+            loaded.push(document);
+        }
+
+        let packs = Self::embedded()
+            .packs
+            .into_iter()
+            .map(|embedded| {
+                match loaded.iter().position(|d| d.pack == embedded.pack) {
+                    Some(pos) => loaded.swap_remove(pos),
+                    None => {
+                        warn!(
+                            "No {} pack in {}, using the embedded one",
+                            embedded.pack,
+                            dir.display()
+                        );
+                        embedded
+                    }
+                }
+            })
+            .collect();
+
+        Ok(Self { packs })
+    }
+
+    /// Document of a built-in pack (custom packs resolve to standard)
+    pub fn get(&self, pack: PolicyPack) -> &PackDocument {
+        let pack = match pack {
+            PolicyPack::Custom => PolicyPack::Standard,
+            pack => pack,
+        };
+
+        self.packs
+            .iter()
+            .find(|d| d.pack == pack)
+            .expect("every built-in pack is loaded")
+    }
+}
+
+/// Policy packs currently in effect
+pub fn current() -> Arc<PackSet> {
+    PACKS.read().expect("policy packs poisoned").clone()
+}
+
+/// Load packs from a directory, if given, and make them current
+pub fn load(dir: Option<&Path>) -> Result<()> {
+    let Some(dir) = dir else {
+        return Ok(());
+    };
+
+    let packs = PackSet::load_dir(dir)?;
+
+    for pack in BUILTIN_PACKS {
+        info!("Loaded {} policy pack v{}", pack, packs.get(pack).version);
+    }
+
+    *PACKS.write().expect("policy packs poisoned") = Arc::new(packs);
+
+    Ok(())
+}
+
+/// Reload packs from `dir` whenever the process receives SIGHUP
+///
+/// An invalid directory keeps the packs already in effect.
+#[cfg(unix)]
+pub async fn reload_on_sighup(dir: PathBuf) -> Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+
+    while hangup.recv().await.is_some() {
+        info!("SIGHUP received, reloading policy packs from {}", dir.display());
+        if let Err(e) = load(Some(&dir)) {
+            warn!("Failed to reload policy packs, keeping current ones: {:#}", e);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_packs_are_valid() {
+        let packs = PackSet::embedded();
+
+        for pack in BUILTIN_PACKS {
+            assert_eq!(packs.get(pack).pack, pack);
+        }
+        assert_eq!(packs.get(PolicyPack::Custom).pack, PolicyPack::Standard);
+    }
+
+    #[test]
+    fn invalid_documents_are_rejected() {
+        let (_, minimal) = EMBEDDED_PACKS[0];
+        let duplicate_file = format!(
+            "{}\n[[files]]\npath = \"README.adoc\"\ndescription = \"Again\"\ncategory = \"documentation\"\npoints = 1\nseverity = \"required\"\nremediation = \"\"\n",
+            minimal
+        );
+
+        let cases = [
+            (
+                minimal.replace("schema = 1", "schema = 2"),
+                "unsupported schema version",
+            ),
+            (
+                minimal.replace("pack = \"minimal\"", "pack = \"custom\""),
+                "custom packs",
+            ),
+            (
+                minimal.replace("version = \"1.0.0\"", "version = \"\""),
+                "version must not be empty",
+            ),
+            (duplicate_file, "duplicate check README.adoc"),
+        ];
+
+        for (document, error) in cases {
+            let e = PackDocument::parse("minimal.toml", &document).unwrap_err();
+            assert!(e.to_string().contains(error), "{}: {}", error, e);
+        }
+    }
+}
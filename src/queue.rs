@@ -0,0 +1,300 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! Webhook job queue
+//!
+//! Deliveries are validated and queued by the HTTP handler, which answers
+//! GitHub straight away, and processed by a fixed pool of workers. Failed jobs
+//! are retried with exponential backoff. A job for a repo and commit that is
+//! already waiting is replaced by the newer delivery instead of queued twice.
+//! On shutdown the queue stops accepting jobs and the workers drain it.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::deliveries::DeliveryStore;
+use crate::metrics;
+use crate::webhook;
+
+/// Delay before the first retry, doubled for every further attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest delay between two attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A webhook delivery waiting to be processed
+#[derive(Debug)]
+pub struct Job {
+    /// Value of the `X-GitHub-Event` header
+    pub event: String,
+    /// Value of the `X-GitHub-Delivery` header, for logs and deduplication
+    pub delivery: Option<String>,
+    /// Raw payload
+    pub body: String,
+}
+
+/// A waiting job with the deliveries it replaced
+#[derive(Debug)]
+struct Pending {
+    job: Job,
+    /// Deliveries of earlier jobs for the same key, acknowledged but not run
+    replaced: Vec<String>,
+}
+
+impl Pending {
+    fn new(job: Job) -> Self {
+        Self {
+            job,
+            replaced: Vec::new(),
+        }
+    }
+
+    /// Replace the waiting job with a newer delivery for the same key
+    fn replace(&mut self, job: Job) {
+        let replaced = std::mem::replace(&mut self.job, job);
+        self.replaced.extend(replaced.delivery);
+    }
+
+    /// Every delivery this job stands for
+    fn deliveries(&self) -> impl Iterator<Item = &str> {
+        self.job.delivery.iter().chain(&self.replaced).map(String::as_str)
+    }
+}
+
+/// What happened to an enqueued job
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Enqueued {
+    /// Added to the end of the queue
+    Queued,
+    /// Replaced a waiting job for the same repo and commit
+    Coalesced,
+}
+
+/// Why a job was not enqueued
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnqueueError {
+    Full,
+    ShuttingDown,
+}
+
+impl std::fmt::Display for EnqueueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnqueueError::Full => write!(f, "job queue is full"),
+            EnqueueError::ShuttingDown => write!(f, "shutting down"),
+        }
+    }
+}
+
+impl std::error::Error for EnqueueError {}
+
+/// Handle to the queue, cheap to clone into request handlers
+#[derive(Clone)]
+pub struct JobQueue {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    /// Waiting jobs by coalescing key; the channel carries the keys in order
+    pending: Mutex<HashMap<String, Pending>>,
+    /// Taken on shutdown so the workers see the channel close
+    sender: Mutex<Option<mpsc::Sender<String>>>,
+    workers: Mutex<JoinSet<()>>,
+    /// Deliveries already acknowledged, forgotten again if their job fails
+    deliveries: Arc<DeliveryStore>,
+    /// Source of keys for jobs that never coalesce
+    next_id: AtomicU64,
+}
+
+impl JobQueue {
+    /// Start `config.workers` workers processing jobs with `config`
+    pub fn start(config: Arc<Config>, deliveries: Arc<DeliveryStore>) -> Self {
+        let (sender, receiver) = mpsc::channel(config.queue_capacity.max(1));
+        let receiver = Arc::new(tokio::sync::Mutex::new(receiver));
+
+        let inner = Arc::new(Inner {
+            pending: Mutex::new(HashMap::new()),
+            sender: Mutex::new(Some(sender)),
+            workers: Mutex::new(JoinSet::new()),
+            deliveries,
+            next_id: AtomicU64::new(0),
+        });
+
+        {
+            let mut workers = inner.workers.lock().expect("job queue poisoned");
+            for worker in 0..config.workers.max(1) {
+                workers.spawn(work(
+                    worker,
+                    config.clone(),
+                    inner.clone(),
+                    receiver.clone(),
+                ));
+            }
+        }
+
+        info!(
+            "Started {} webhook workers (queue capacity {})",
+            config.workers.max(1),
+            config.queue_capacity.max(1)
+        );
+
+        Self { inner }
+    }
+
+    /// Queue a job, coalescing it with a waiting job for the same repo and commit
+    pub fn enqueue(&self, job: Job, payload: &serde_json::Value) -> Result<Enqueued, EnqueueError> {
+        let key = webhook::job_key(&job.event, payload).unwrap_or_else(|| {
+            let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+            format!("#{}", id)
+        });
+
+        let sender = self.inner.sender.lock().expect("job queue poisoned");
+        let Some(sender) = sender.as_ref() else {
+            return Err(EnqueueError::ShuttingDown);
+        };
+
+        let mut pending = self.inner.pending.lock().expect("job queue poisoned");
+        if let Some(waiting) = pending.get_mut(&key) {
+            waiting.replace(job);
+            return Ok(Enqueued::Coalesced);
+        }
+
+        match sender.try_send(key.clone()) {
+            Ok(()) => {
+                pending.insert(key, Pending::new(job));
+                Ok(Enqueued::Queued)
+            }
+            Err(mpsc::error::TrySendError::Full(_)) => Err(EnqueueError::Full),
+            Err(mpsc::error::TrySendError::Closed(_)) => Err(EnqueueError::ShuttingDown),
+        }
+    }
+
+    /// Number of jobs waiting to be processed
+    pub fn depth(&self) -> usize {
+        self.inner.pending.lock().expect("job queue poisoned").len()
+    }
+
+    /// Stop accepting jobs and wait up to `timeout` for the queue to drain
+    pub async fn shutdown(&self, timeout: Duration) {
+        self.inner.sender.lock().expect("job queue poisoned").take();
+
+        let mut workers = std::mem::take(&mut *self.inner.workers.lock().expect("job queue poisoned"));
+        info!("Draining {} queued webhook jobs", self.depth());
+
+        let drained = tokio::time::timeout(timeout, async {
+            while workers.join_next().await.is_some() {}
+        })
+        .await;
+
+        if drained.is_err() {
+            warn!(
+                "Gave up draining the job queue after {:?}, {} jobs dropped",
+                timeout,
+                self.depth()
+            );
+            workers.abort_all();
+        }
+    }
+}
+
+/// Worker loop: take jobs until the queue is closed and empty
+async fn work(
+    worker: usize,
+    config: Arc<Config>,
+    inner: Arc<Inner>,
+    receiver: Arc<tokio::sync::Mutex<mpsc::Receiver<String>>>,
+) {
+    loop {
+        let key = receiver.lock().await.recv().await;
+        let Some(key) = key else {
+            break;
+        };
+
+        // Later deliveries for the same key are queued anew once this one is taken
+        let pending = inner.pending.lock().expect("job queue poisoned").remove(&key);
+        let Some(pending) = pending else {
+            continue;
+        };
+
+        let succeeded = process(worker, &config, &pending.job).await;
+        metrics::webhook_job(&pending.job.event, succeeded);
+        if !succeeded {
+            // Let a redelivery of this or any replaced delivery try again
+            for delivery in pending.deliveries() {
+                inner.deliveries.remove(delivery);
+            }
+        }
+    }
+}
+
+/// Run a job, retrying with exponential backoff, `false` if it never succeeded
+///
+/// Payloads that cannot be parsed are not retried.
+async fn process(worker: usize, config: &Config, job: &Job) -> bool {
+    let delivery = job.delivery.as_deref().unwrap_or("-");
+    let attempts = config.job_attempts.max(1);
+    let mut state = webhook::JobState::default();
+    let mut backoff = INITIAL_BACKOFF;
+
+    for attempt in 1..=attempts {
+        state.last_attempt = attempt == attempts;
+        match webhook::handle_event(config, &mut state, &job.event, &job.body).await {
+            Ok(()) => return true,
+            Err(e) if e.is::<webhook::InvalidPayload>() => {
+                warn!(
+                    "Worker {}: {} delivery {} failed, not retrying: {:#}",
+                    worker, job.event, delivery, e
+                );
+                return false;
+            }
+            Err(e) if attempt < attempts => {
+                warn!(
+                    "Worker {}: {} delivery {} failed (attempt {}/{}), retrying in {:?}: {:#}",
+                    worker, job.event, delivery, attempt, attempts, backoff, e
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+            Err(e) => {
+                warn!(
+                    "Worker {}: {} delivery {} failed after {} attempts: {:#}",
+                    worker, job.event, delivery, attempt, e
+                );
+            }
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(delivery: Option<&str>) -> Job {
+        Job {
+            event: "push".to_string(),
+            delivery: delivery.map(str::to_string),
+            body: String::new(),
+        }
+    }
+
+    #[test]
+    fn replaced_deliveries_stay_with_the_job() {
+        let mut pending = Pending::new(job(Some("first")));
+        pending.replace(job(None));
+        pending.replace(job(Some("second")));
+        pending.replace(job(Some("third")));
+
+        assert_eq!(pending.job.delivery.as_deref(), Some("third"));
+        assert_eq!(
+            pending.deliveries().collect::<Vec<_>>(),
+            ["third", "first", "second"]
+        );
+    }
+}
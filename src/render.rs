@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! Report rendering
+//!
+//! Renders a compliance report as JSON, SARIF, JUnit XML, HTML, AsciiDoc or
+//! Markdown, for the `check` command and the check endpoint. The text formats
+//! come from templates, so they can be overridden like any other message.
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::rsr::ComplianceReport;
+use crate::sarif;
+use crate::templates;
+
+/// How a compliance report is printed or served
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    /// Markdown, as in check runs
+    #[value(alias = "text")]
+    #[serde(alias = "text")]
+    Markdown,
+    /// The report as JSON
+    Json,
+    /// SARIF 2.1.0, e.g. for GitHub code scanning
+    Sarif,
+    /// JUnit XML with a test case per check, for CI dashboards
+    Junit,
+    /// Standalone HTML page
+    Html,
+    /// AsciiDoc document
+    Asciidoc,
+}
+
+impl ReportFormat {
+    /// Media type of a report in this format
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Markdown => "text/markdown; charset=utf-8",
+            Self::Json => "application/json",
+            Self::Sarif => "application/sarif+json",
+            Self::Junit => "application/xml",
+            Self::Html => "text/html; charset=utf-8",
+            Self::Asciidoc => "text/asciidoc; charset=utf-8",
+        }
+    }
+
+    /// Format of a media type, if one renders it
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            "application/json" => Some(Self::Json),
+            "application/sarif+json" => Some(Self::Sarif),
+            "application/xml" | "text/xml" | "application/junit+xml" => Some(Self::Junit),
+            "text/html" => Some(Self::Html),
+            "text/asciidoc" | "text/x-asciidoc" => Some(Self::Asciidoc),
+            "text/markdown" | "text/x-markdown" | "text/plain" => Some(Self::Markdown),
+            _ => None,
+        }
+    }
+
+    /// Most preferred format of an `Accept` header, `None` if any will do
+    ///
+    /// Media types are ranked by quality, then by their order in the header.
+    pub fn from_accept(accept: &str) -> Option<Self> {
+        let mut candidates: Vec<(f32, Self)> = accept
+            .split(',')
+            .filter_map(|range| {
+                let mut params = range.split(';');
+                let format = Self::from_media_type(&params.next()?.trim().to_ascii_lowercase())?;
+                let quality = params
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (quality > 0.0).then_some((quality, format))
+            })
+            .collect();
+
+        // Stable, so equal qualities keep the header's order
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        candidates.first().map(|(_, format)| *format)
+    }
+}
+
+/// Render a report in a format
+pub fn render(report: &ComplianceReport, format: ReportFormat) -> Result<String> {
+    match format {
+        ReportFormat::Json => Ok(serde_json::to_string_pretty(report)?),
+        ReportFormat::Sarif => Ok(serde_json::to_string_pretty(&sarif::from_report(report))?),
+        ReportFormat::Junit => templates::report_document("report.junit.xml", report),
+        ReportFormat::Html => templates::report_document("report.html", report),
+        ReportFormat::Asciidoc => templates::report_document("report.adoc", report),
+        ReportFormat::Markdown => templates::report_document("report-document.md", report),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsr::tests::{check, report};
+    use crate::rsr::{CheckStatus, Severity};
+
+    #[test]
+    fn markdown_reports_lead_with_score_commit_and_summary() {
+        let mut report = report(vec![
+            check("README.adoc", Severity::Required, CheckStatus::Pass),
+            check("SECURITY.md", Severity::Recommended, CheckStatus::Warn),
+        ]);
+        report.summary = "Summary line".to_string();
+
+        let text = render(&report, ReportFormat::Markdown).unwrap();
+        assert!(text.starts_with(
+            "# RSR Compliance: owner/repo (5/10, 50%)\n\nCommit: abc123\n\nSummary line\n\n## Policy: standard"
+        ), "{}", text);
+
+        report.commit_sha = None;
+        let text = render(&report, ReportFormat::Markdown).unwrap();
+        assert!(text.contains("50%)\n\nSummary line\n\n"), "{}", text);
+    }
+
+    #[test]
+    fn accept_headers_pick_the_preferred_format() {
+        let cases = [
+            ("application/sarif+json", Some(ReportFormat::Sarif)),
+            ("TEXT/HTML", Some(ReportFormat::Html)),
+            ("text/asciidoc; charset=utf-8", Some(ReportFormat::Asciidoc)),
+            ("text/plain", Some(ReportFormat::Markdown)),
+            (
+                "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+                Some(ReportFormat::Html),
+            ),
+            (
+                "application/json;q=0.5, text/markdown",
+                Some(ReportFormat::Markdown),
+            ),
+            (
+                "application/xml;q=0.9, application/json;q=0.9",
+                Some(ReportFormat::Junit),
+            ),
+            ("application/json;q=oops", Some(ReportFormat::Json)),
+            (
+                "text/html;q=0, application/json;q=0.1",
+                Some(ReportFormat::Json),
+            ),
+            ("text/html;q=0", None),
+            ("*/*", None),
+            ("", None),
+        ];
+
+        for (accept, expected) in cases {
+            assert_eq!(ReportFormat::from_accept(accept), expected, "{}", accept);
+        }
+    }
+
+    #[test]
+    fn junit_reports_fail_skip_and_escape_checks() {
+        let mut failed = check("no-*.ts", Severity::Required, CheckStatus::Fail);
+        failed.message = "Found <script> & \"friends\"".to_string();
+        let report = report(vec![
+            check("README.adoc", Severity::Required, CheckStatus::Pass),
+            failed,
+            check("STATE.scm", Severity::Optional, CheckStatus::Skip),
+        ]);
+
+        let xml = render(&report, ReportFormat::Junit).unwrap();
+        assert!(
+            xml.contains(r#"tests="3" failures="1" skipped="1""#),
+            "{}",
+            xml
+        );
+        assert!(
+            xml.contains("Found &lt;script&gt; &amp; &quot;friends&quot;"),
+            "{}",
+            xml
+        );
+        assert!(!xml.contains("<script>"), "{}", xml);
+        assert!(
+            xml.contains(r#"<skipped message="STATE.scm Skip"/>"#),
+            "{}",
+            xml
+        );
+    }
+}
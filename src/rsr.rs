@@ -4,28 +4,40 @@
 //!
 //! Supports policy packs and opt-in severity levels for flexible compliance.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
 
-use crate::config::Config;
-use crate::github::GitHubClient;
+use crate::checks::{CheckContext, CheckRegistry, Evaluation};
+use crate::metrics;
+use crate::policy;
+use crate::source::RepoSource;
 
 /// Severity levels for compliance checks
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+///
+/// Ordered from strictest to most lenient, so the stricter of two is the `min`.
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum Severity {
     /// Must pass for RSR compliance
     Required,
     /// Should pass, counts toward score but doesn't block
+    #[default]
     Recommended,
     /// Nice to have, informational only
     Optional,
 }
 
-impl Default for Severity {
-    fn default() -> Self {
-        Self::Recommended
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Required => write!(f, "required"),
+            Self::Recommended => write!(f, "recommended"),
+            Self::Optional => write!(f, "optional"),
+        }
     }
 }
 
@@ -70,179 +82,208 @@ pub struct RepoConfig {
     /// Checks to skip entirely
     #[serde(default)]
     pub skip: Vec<String>,
-    /// Additional required files beyond the policy
+    /// Additional required files beyond the policy (scored as required)
     #[serde(default)]
     pub require: Vec<String>,
-    /// Custom banned patterns
+    /// Custom banned patterns (reported as `no-<pattern>`, required)
     #[serde(default)]
     pub ban: Vec<String>,
+    /// Custom policy pack definition, used with `policy = "custom"`
+    #[serde(default)]
+    pub custom: Option<CustomPolicy>,
+    /// Open pull requests scaffolding missing files (default true)
+    #[serde(default)]
+    pub autofix: Option<bool>,
 }
 
-/// Check definition with severity per policy pack
-#[derive(Debug, Clone)]
-pub struct CheckDef {
-    pub name: &'static str,
-    pub description: &'static str,
-    pub category: CheckCategory,
-    pub points: u8,
-    /// Severity by policy pack: (minimal, standard, strict, enterprise)
-    pub severity: (Severity, Severity, Severity, Severity),
-}
-
-impl CheckDef {
-    pub fn severity_for(&self, policy: PolicyPack) -> Severity {
-        match policy {
-            PolicyPack::Minimal => self.severity.0,
-            PolicyPack::Standard => self.severity.1,
-            PolicyPack::Strict => self.severity.2,
-            PolicyPack::Enterprise => self.severity.3,
-            PolicyPack::Custom => self.severity.1, // Default to standard for custom
+impl RepoConfig {
+    /// Whether rhodibot may open scaffold pull requests
+    pub fn autofix_enabled(&self) -> bool {
+        self.autofix.unwrap_or(true)
+    }
+
+    /// Custom pack in effect, if the repo uses `policy = "custom"`
+    ///
+    /// A custom policy without a `[custom]` table behaves like the standard pack.
+    pub fn custom_policy(&self) -> Option<CustomPolicy> {
+        (self.policy == PolicyPack::Custom).then(|| self.custom.clone().unwrap_or_default())
+    }
+
+    /// Severity a check has under this configuration, `None` if it is not checked
+    fn severity_of(&self, name: &str) -> Option<Severity> {
+        if self.skip.iter().any(|check| check == name) {
+            return None;
         }
+
+        let custom = self.custom_policy();
+        let packs = policy::current();
+        let pack = packs.get(custom.as_ref().map_or(self.policy, CustomPolicy::base));
+        let registry = CheckRegistry::for_repo(pack, self);
+
+        registry
+            .get(name)
+            .map(|(_, severity)| resolve_severity(self, custom.as_ref(), name, severity))
     }
 }
 
-/// Required files for RSR compliance with policy-based severity
-pub const REQUIRED_FILES: &[CheckDef] = &[
-    CheckDef {
-        name: "README.adoc",
-        description: "AsciiDoc README",
-        category: CheckCategory::Documentation,
-        points: 5,
-        // (minimal, standard, strict, enterprise)
-        severity: (Severity::Required, Severity::Required, Severity::Required, Severity::Required),
-    },
-    CheckDef {
-        name: "LICENSE.txt",
-        description: "License file",
-        category: CheckCategory::Governance,
-        points: 5,
-        severity: (Severity::Required, Severity::Required, Severity::Required, Severity::Required),
-    },
-    CheckDef {
-        name: "SECURITY.md",
-        description: "Security policy",
-        category: CheckCategory::Security,
-        points: 5,
-        severity: (Severity::Optional, Severity::Recommended, Severity::Required, Severity::Required),
-    },
-    CheckDef {
-        name: "CONTRIBUTING.md",
-        description: "Contributing guidelines",
-        category: CheckCategory::Documentation,
-        points: 3,
-        severity: (Severity::Optional, Severity::Recommended, Severity::Required, Severity::Required),
-    },
-    CheckDef {
-        name: "CODE_OF_CONDUCT.md",
-        description: "Code of conduct",
-        category: CheckCategory::Governance,
-        points: 3,
-        severity: (Severity::Optional, Severity::Optional, Severity::Recommended, Severity::Required),
-    },
-    CheckDef {
-        name: ".claude/CLAUDE.md",
-        description: "AI assistant instructions",
-        category: CheckCategory::Structure,
-        points: 2,
-        severity: (Severity::Optional, Severity::Optional, Severity::Recommended, Severity::Required),
-    },
-    CheckDef {
-        name: "STATE.scm",
-        description: "Project state file",
-        category: CheckCategory::Structure,
-        points: 3,
-        severity: (Severity::Optional, Severity::Recommended, Severity::Required, Severity::Required),
-    },
-    CheckDef {
-        name: "META.scm",
-        description: "Meta information",
-        category: CheckCategory::Structure,
-        points: 3,
-        severity: (Severity::Optional, Severity::Recommended, Severity::Required, Severity::Required),
-    },
-    CheckDef {
-        name: "ECOSYSTEM.scm",
-        description: "Ecosystem position",
-        category: CheckCategory::Structure,
-        points: 3,
-        severity: (Severity::Optional, Severity::Optional, Severity::Recommended, Severity::Required),
-    },
-];
+/// Severity of a check: repo overrides, then the custom pack, then the base pack
+fn resolve_severity(
+    repo_config: &RepoConfig,
+    custom: Option<&CustomPolicy>,
+    name: &str,
+    pack_severity: Severity,
+) -> Severity {
+    let custom_severity = custom.and_then(|c| c.severities.get(name));
 
-/// Banned patterns definition
-#[derive(Debug, Clone)]
-pub struct BannedPattern {
-    pub name: &'static str,
-    pub description: &'static str,
-    pub category: CheckCategory,
-    /// Severity by policy pack: (minimal, standard, strict, enterprise)
-    pub severity: (Severity, Severity, Severity, Severity),
-}
-
-impl BannedPattern {
-    pub fn severity_for(&self, policy: PolicyPack) -> Severity {
-        match policy {
-            PolicyPack::Minimal => self.severity.0,
-            PolicyPack::Standard => self.severity.1,
-            PolicyPack::Strict => self.severity.2,
-            PolicyPack::Enterprise => self.severity.3,
-            PolicyPack::Custom => self.severity.1,
+    repo_config
+        .severity_overrides
+        .get(name)
+        .or(custom_severity)
+        .copied()
+        .unwrap_or(pack_severity)
+}
+
+/// Score thresholds (percentages) deciding a report's outcome
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// Minimum score for a passing report
+    pub pass: f32,
+    /// Minimum score for a neutral report, below which it fails
+    pub neutral: f32,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            pass: 70.0,
+            neutral: 50.0,
         }
     }
 }
 
-/// Banned file patterns (CCCP language policy)
-pub const BANNED_PATTERNS: &[BannedPattern] = &[
-    BannedPattern {
-        name: "package-lock.json",
-        description: "npm lock file (use Deno)",
-        category: CheckCategory::LanguagePolicy,
-        severity: (Severity::Optional, Severity::Recommended, Severity::Required, Severity::Required),
-    },
-    BannedPattern {
-        name: "yarn.lock",
-        description: "Yarn lock file (use Deno)",
-        category: CheckCategory::LanguagePolicy,
-        severity: (Severity::Optional, Severity::Recommended, Severity::Required, Severity::Required),
-    },
-    BannedPattern {
-        name: "pnpm-lock.yaml",
-        description: "pnpm lock file (use Deno)",
-        category: CheckCategory::LanguagePolicy,
-        severity: (Severity::Optional, Severity::Recommended, Severity::Required, Severity::Required),
-    },
-    BannedPattern {
-        name: "bun.lockb",
-        description: "Bun lock file (use Deno)",
-        category: CheckCategory::LanguagePolicy,
-        severity: (Severity::Optional, Severity::Recommended, Severity::Required, Severity::Required),
-    },
-    BannedPattern {
-        name: "go.mod",
-        description: "Go module (use Rust)",
-        category: CheckCategory::LanguagePolicy,
-        severity: (Severity::Optional, Severity::Recommended, Severity::Required, Severity::Required),
-    },
-    BannedPattern {
-        name: "go.sum",
-        description: "Go checksum (use Rust)",
-        category: CheckCategory::LanguagePolicy,
-        severity: (Severity::Optional, Severity::Recommended, Severity::Required, Severity::Required),
-    },
-];
+/// Custom policy pack defined in .rsr.toml
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CustomPolicy {
+    /// Built-in pack providing the severities not set here
+    #[serde(default)]
+    pub extends: PolicyPack,
+    /// Severity per check, replacing the base pack's
+    #[serde(default)]
+    pub severities: BTreeMap<String, Severity>,
+    /// Points per check, replacing the built-in weights
+    #[serde(default)]
+    pub points: BTreeMap<String, u8>,
+    /// Checks added by the pack
+    #[serde(default)]
+    pub checks: Vec<CustomCheck>,
+    /// Minimum score for a passing report (default 70)
+    pub pass_threshold: Option<f32>,
+    /// Minimum score for a neutral report (default 50)
+    pub neutral_threshold: Option<f32>,
+}
+
+impl CustomPolicy {
+    /// Built-in pack the custom pack extends
+    pub fn base(&self) -> PolicyPack {
+        match self.extends {
+            PolicyPack::Custom => PolicyPack::Standard,
+            pack => pack,
+        }
+    }
+
+    pub fn thresholds(&self) -> Thresholds {
+        let default = Thresholds::default();
+
+        Thresholds {
+            pass: self.pass_threshold.unwrap_or(default.pass),
+            neutral: self.neutral_threshold.unwrap_or(default.neutral),
+        }
+    }
+
+    /// Human-readable list of the rules this pack applies
+    pub fn rules(&self) -> Vec<String> {
+        let mut rules = vec![format!("extends the {} pack", self.base())];
+
+        for (check, severity) in &self.severities {
+            rules.push(format!("{} is {}", check, severity));
+        }
+        for (check, points) in &self.points {
+            rules.push(format!("{} is worth {} points", check, points));
+        }
+        for check in &self.checks {
+            match check.kind() {
+                Some(kind) => rules.push(format!("adds {} ({})", kind, check.severity)),
+                None => rules.push("ignores a check without `require` or `ban`".to_string()),
+            }
+        }
+        if let Some(pass) = self.pass_threshold {
+            rules.push(format!("pass threshold {:.0}%", pass));
+        }
+        if let Some(neutral) = self.neutral_threshold {
+            rules.push(format!("neutral threshold {:.0}%", neutral));
+        }
+
+        rules
+    }
+}
+
+/// Extra check in a custom policy pack
+///
+/// Exactly one of `require` and `ban` must be set.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CustomCheck {
+    /// File or directory that must exist
+    pub require: Option<String>,
+    /// Gitignore-style glob that must not match
+    pub ban: Option<String>,
+    /// Description shown in reports
+    pub description: Option<String>,
+    #[serde(default = "CustomCheck::default_severity")]
+    pub severity: Severity,
+    /// Points awarded when a required file is present
+    pub points: Option<u8>,
+}
+
+impl CustomCheck {
+    fn default_severity() -> Severity {
+        Severity::Required
+    }
+
+    /// Check id as shown in reports, `None` if the definition is invalid
+    pub fn kind(&self) -> Option<String> {
+        match (&self.require, &self.ban) {
+            (Some(path), None) => Some(path.clone()),
+            (None, Some(pattern)) => Some(format!("no-{}", pattern)),
+            _ => None,
+        }
+    }
+}
 
 /// RSR Compliance Report
 #[derive(Debug, Serialize)]
 pub struct ComplianceReport {
     pub owner: String,
     pub repo: String,
+    /// Commit the report describes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_sha: Option<String>,
     pub policy: PolicyPack,
-    pub score: u8,
-    pub max_score: u8,
+    /// Version of the policy pack the report was evaluated against
+    pub pack_version: String,
+    pub score: u32,
+    pub max_score: u32,
     pub percentage: f32,
+    /// Thresholds the report's outcome is judged against
+    pub thresholds: Thresholds,
+    /// Rules of a custom policy pack that were applied
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub custom_rules: Vec<String>,
     pub required_passed: bool,
     pub checks: Vec<Check>,
     pub summary: String,
+    /// Whether the commit has a `.rsr.toml`
+    #[serde(skip)]
+    pub has_repo_config: bool,
 }
 
 /// Individual compliance check
@@ -255,6 +296,28 @@ pub struct Check {
     pub points: u8,
     pub max_points: u8,
     pub message: String,
+    /// Files, or lines of them, the check flags
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub locations: Vec<Location>,
+}
+
+/// A file, or a line of one, flagged by a check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub path: String,
+    /// 1-based line, `None` for the file as a whole
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+}
+
+impl Location {
+    /// The whole of a file
+    pub fn file(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            line: None,
+        }
+    }
 }
 
 /// Check categories
@@ -272,8 +335,20 @@ pub enum CheckCategory {
     LanguagePolicy,
 }
 
+impl std::fmt::Display for CheckCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Documentation => write!(f, "Documentation"),
+            Self::Security => write!(f, "Security"),
+            Self::Governance => write!(f, "Governance"),
+            Self::Structure => write!(f, "Structure"),
+            Self::LanguagePolicy => write!(f, "Language Policy"),
+        }
+    }
+}
+
 /// Check status
-#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum CheckStatus {
     #[serde(rename = "pass")]
     Pass,
@@ -285,262 +360,301 @@ pub enum CheckStatus {
     Skip,
 }
 
-/// Load repository configuration from .rsr.toml
-pub async fn load_repo_config(client: &GitHubClient, owner: &str, repo: &str) -> RepoConfig {
-    match client.get_file_content(owner, repo, ".rsr.toml").await {
-        Ok(content) => {
-            toml::from_str(&content).unwrap_or_else(|e| {
-                tracing::warn!("Failed to parse .rsr.toml: {}", e);
-                RepoConfig::default()
-            })
+/// Path of a repository's own configuration
+pub const REPO_CONFIG_PATH: &str = ".rsr.toml";
+
+/// Path of the org-wide configuration in the organization's `.github` repository
+pub const ORG_CONFIG_PATH: &str = ".github/rsr.toml";
+
+/// Settings an organization locks for all of its repositories
+#[derive(Debug, Deserialize, Default)]
+pub struct LockedSettings {
+    /// Checks repositories cannot skip or change the severity of
+    #[serde(default)]
+    pub checks: Vec<String>,
+    /// Repositories cannot change the policy pack
+    #[serde(default)]
+    pub policy: bool,
+}
+
+/// Load repository configuration from the .rsr.toml at the source's commit
+///
+/// The repository's settings are layered on top of the org-wide defaults
+/// from [`ORG_CONFIG_PATH`], minus any settings the organization locked.
+/// Locked checks keep at least the severity the org-wide configuration gives
+/// them, whichever pack the repository selects. An invalid `.rsr.toml` is
+/// ignored, so the org-wide configuration applies as it is.
+pub async fn load_repo_config(source: &dyn RepoSource) -> RepoConfig {
+    let mut config = match source.org_config().await {
+        Some(content) => parse_config_table(&content, ORG_CONFIG_PATH),
+        None => toml::Table::new(),
+    };
+
+    let locked: LockedSettings = match config.remove("locked") {
+        Some(locked) => locked.try_into().unwrap_or_else(|e| {
+            tracing::warn!("Failed to parse locked settings in {}: {}", ORG_CONFIG_PATH, e);
+            LockedSettings::default()
+        }),
+        None => LockedSettings::default(),
+    };
+
+    let org: RepoConfig = toml::Value::Table(config.clone())
+        .try_into()
+        .unwrap_or_else(|e| {
+            tracing::warn!("Invalid RSR configuration in {}: {}", ORG_CONFIG_PATH, e);
+            RepoConfig::default()
+        });
+
+    // A broken repository layer is dropped as a whole, leaving the org's
+    let mut repo_config = org.clone();
+    if let Ok(content) = source.read_file(REPO_CONFIG_PATH).await {
+        let mut repo = parse_config_table(&content, REPO_CONFIG_PATH);
+        strip_locked(&mut repo, &locked);
+        merge_tables(&mut config, repo);
+
+        match toml::Value::Table(config).try_into() {
+            Ok(merged) => repo_config = merged,
+            Err(e) => tracing::warn!("Ignoring invalid {}: {}", REPO_CONFIG_PATH, e),
         }
-        Err(_) => RepoConfig::default(),
     }
+
+    if !locked.checks.is_empty() {
+        enforce_locked_severities(&mut repo_config, &org, &locked);
+    }
+
+    repo_config
 }
 
-/// Check repository compliance with RSR
-pub async fn check_compliance(config: &Config, owner: &str, repo: &str) -> Result<ComplianceReport> {
-    let client = GitHubClient::new(config);
+/// Parse a configuration file as a TOML table, empty if it is invalid
+fn parse_config_table(content: &str, path: &str) -> toml::Table {
+    content.parse().unwrap_or_else(|e| {
+        tracing::warn!("Failed to parse {}: {}", path, e);
+        toml::Table::new()
+    })
+}
+
+/// Remove repository settings that would override locked org settings
+fn strip_locked(repo: &mut toml::Table, locked: &LockedSettings) {
+    if locked.policy {
+        for key in ["policy", "custom"] {
+            if repo.remove(key).is_some() {
+                tracing::warn!("Ignoring `{}` in .rsr.toml: policy is locked by the organization", key);
+            }
+        }
+    }
+
+    let is_locked = |check: &str| locked.checks.iter().any(|c| c == check);
+
+    if let Some(toml::Value::Array(skip)) = repo.get_mut("skip") {
+        skip.retain(|check| match check.as_str() {
+            Some(check) if is_locked(check) => {
+                tracing::warn!("Ignoring skip of locked check {}", check);
+                false
+            }
+            _ => true,
+        });
+    }
+
+    let keep_unlocked = |check: &str, _: &mut toml::Value| {
+        let keep = !is_locked(check);
+        if !keep {
+            tracing::warn!("Ignoring severity override of locked check {}", check);
+        }
+        keep
+    };
+
+    if let Some(toml::Value::Table(overrides)) = repo.get_mut("severity_overrides") {
+        overrides.retain(keep_unlocked);
+    }
+    if let Some(toml::Value::Table(custom)) = repo.get_mut("custom")
+        && let Some(toml::Value::Table(severities)) = custom.get_mut("severities")
+    {
+        severities.retain(keep_unlocked);
+    }
+}
+
+/// Raise locked checks to at least the severity the org configuration gives them
+///
+/// A repository choosing a more lenient pack would otherwise relax a locked
+/// check, or drop it if its pack does not define the check at all.
+fn enforce_locked_severities(config: &mut RepoConfig, org: &RepoConfig, locked: &LockedSettings) {
+    for name in &locked.checks {
+        let Some(floor) = org.severity_of(name) else {
+            continue;
+        };
+
+        match config.severity_of(name) {
+            Some(severity) if severity <= floor => continue,
+            Some(_) => {}
+            None => match name.strip_prefix("no-") {
+                Some(pattern) => config.ban.push(pattern.to_string()),
+                None => config.require.push(name.clone()),
+            },
+        }
+
+        tracing::info!("Checking locked check {} as {}, as the organization does", name, floor);
+        config.severity_overrides.insert(name.clone(), floor);
+    }
+}
+
+/// Layer one configuration table over another
+///
+/// Tables are merged recursively, lists are combined and any other value in
+/// `layer` replaces the one in `base`.
+fn merge_tables(base: &mut toml::Table, layer: toml::Table) {
+    for (key, value) in layer {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(layer)) => {
+                merge_tables(base, layer)
+            }
+            (Some(toml::Value::Array(base)), toml::Value::Array(layer)) => {
+                for item in layer {
+                    if !base.contains(&item) {
+                        base.push(item);
+                    }
+                }
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
 
+/// Check repository compliance with RSR
+pub async fn check_compliance(source: &dyn RepoSource) -> Result<ComplianceReport> {
     // Load repo-specific configuration
-    let repo_config = load_repo_config(&client, owner, repo).await;
+    let repo_config = load_repo_config(source).await;
 
-    check_compliance_with_policy(config, owner, repo, &repo_config).await
+    check_compliance_with_policy(source, &repo_config).await
 }
 
 /// Check repository compliance with a specific policy configuration
 pub async fn check_compliance_with_policy(
-    config: &Config,
-    owner: &str,
-    repo: &str,
+    source: &dyn RepoSource,
     repo_config: &RepoConfig,
 ) -> Result<ComplianceReport> {
-    let client = GitHubClient::new(config);
+    let started = Instant::now();
     let policy = repo_config.policy;
+    let custom = repo_config.custom_policy();
+    let base_policy = custom.as_ref().map_or(policy, CustomPolicy::base);
+    let thresholds = custom
+        .as_ref()
+        .map(CustomPolicy::thresholds)
+        .unwrap_or_default();
+    let packs = policy::current();
+    let pack = packs.get(base_policy);
+    let registry = CheckRegistry::for_repo(pack, repo_config);
+
+    let ctx = CheckContext {
+        source,
+        tree: source.list_tree().await?.into_iter().collect(),
+    };
 
     let mut checks = Vec::new();
-    let mut total_score = 0u8;
-    let mut max_score = 0u8;
+    let mut total_score = 0u32;
+    let mut max_score = 0u32;
     let mut required_passed = true;
 
-    // Check required files
-    for check_def in REQUIRED_FILES {
+    for (check, pack_severity) in registry.iter() {
+        let name = check.id();
+
         // Skip if explicitly configured to skip
-        if repo_config.skip.contains(&check_def.name.to_string()) {
+        if repo_config.skip.contains(&name) {
             checks.push(Check {
-                name: check_def.name.to_string(),
-                category: check_def.category,
+                name,
+                category: check.category(),
                 severity: Severity::Optional,
                 status: CheckStatus::Skip,
                 points: 0,
                 max_points: 0,
-                message: format!("{} skipped by config", check_def.description),
+                message: format!("{} skipped by config", check.description()),
+                locations: Vec::new(),
             });
             continue;
         }
 
-        // Determine severity (allow override from repo config)
-        let severity = repo_config
-            .severity_overrides
-            .get(check_def.name)
+        let severity = resolve_severity(repo_config, custom.as_ref(), &name, pack_severity);
+        let weight = custom
+            .as_ref()
+            .and_then(|c| c.points.get(&name))
             .copied()
-            .unwrap_or_else(|| check_def.severity_for(policy));
+            .unwrap_or_else(|| check.points());
 
-        // Skip optional checks in scoring
-        if severity == Severity::Optional {
-            let exists = client.file_exists(owner, repo, check_def.name).await;
-            checks.push(Check {
-                name: check_def.name.to_string(),
-                category: check_def.category,
-                severity,
-                status: if exists { CheckStatus::Pass } else { CheckStatus::Skip },
-                points: 0,
-                max_points: 0,
-                message: if exists {
-                    format!("{} found (optional)", check_def.description)
-                } else {
-                    format!("{} not present (optional)", check_def.description)
-                },
-            });
+        let Some(evaluation) = check
+            .evaluate(&ctx)
+            .await
+            .with_context(|| format!("Failed to evaluate {}", name))?
+        else {
             continue;
-        }
-
-        max_score += check_def.points;
-        let exists = client.file_exists(owner, repo, check_def.name).await;
+        };
 
-        if exists {
-            total_score += check_def.points;
-            checks.push(Check {
-                name: check_def.name.to_string(),
-                category: check_def.category,
-                severity,
-                status: CheckStatus::Pass,
-                points: check_def.points,
-                max_points: check_def.points,
-                message: format!("{} found", check_def.description),
-            });
+        // Optional checks are informational and not scored
+        let max_points = if severity == Severity::Optional {
+            0
         } else {
-            let status = match severity {
-                Severity::Required => {
-                    required_passed = false;
-                    CheckStatus::Fail
-                }
-                Severity::Recommended => CheckStatus::Warn,
-                Severity::Optional => CheckStatus::Skip,
-            };
-
-            checks.push(Check {
-                name: check_def.name.to_string(),
-                category: check_def.category,
-                severity,
-                status,
-                points: 0,
-                max_points: check_def.points,
-                message: format!("{} missing", check_def.description),
-            });
-        }
-    }
-
-    // Check for banned patterns
-    for banned in BANNED_PATTERNS {
-        if repo_config.skip.contains(&format!("no-{}", banned.name)) {
-            continue;
-        }
-
-        let severity = repo_config
-            .severity_overrides
-            .get(&format!("no-{}", banned.name))
-            .copied()
-            .unwrap_or_else(|| banned.severity_for(policy));
+            weight
+        };
+        max_score += max_points as u32;
 
-        let exists = client.file_exists(owner, repo, banned.name).await;
+        let failed_status = match severity {
+            Severity::Required => CheckStatus::Fail,
+            Severity::Recommended => CheckStatus::Warn,
+            Severity::Optional => CheckStatus::Skip,
+        };
 
-        if exists {
-            let status = match severity {
-                Severity::Required => {
-                    required_passed = false;
+        let (status, points, mut message, locations) = match evaluation {
+            Evaluation::Pass(message) => (CheckStatus::Pass, max_points, message, Vec::new()),
+            Evaluation::Missing(message) => (failed_status, 0, message, Vec::new()),
+            Evaluation::Partial {
+                points,
+                message,
+                locations,
+            } => {
+                let status = if severity == Severity::Required {
                     CheckStatus::Fail
-                }
-                Severity::Recommended => CheckStatus::Warn,
-                Severity::Optional => CheckStatus::Warn,
-            };
+                } else {
+                    CheckStatus::Warn
+                };
+                // Scale partial credit to a custom weight
+                let points = if check.points() > 0 {
+                    (points as u32 * max_points as u32 / check.points() as u32) as u8
+                } else {
+                    0
+                };
+                (status, points.min(max_points), message, locations)
+            }
+            Evaluation::Violation { message, locations } => {
+                // Violations always warn, even when optional
+                let status = if failed_status == CheckStatus::Skip {
+                    CheckStatus::Warn
+                } else {
+                    failed_status
+                };
+                (status, 0, message, locations)
+            }
+        };
 
-            checks.push(Check {
-                name: format!("no-{}", banned.name),
-                category: banned.category,
-                severity,
-                status,
-                points: 0,
-                max_points: 0,
-                message: format!("{} detected - policy violation", banned.description),
-            });
+        if status == CheckStatus::Fail {
+            required_passed = false;
         }
-    }
-
-    // Check for workflow files
-    let workflow_severity = match policy {
-        PolicyPack::Minimal => Severity::Optional,
-        PolicyPack::Standard => Severity::Recommended,
-        PolicyPack::Strict | PolicyPack::Enterprise => Severity::Required,
-        PolicyPack::Custom => repo_config
-            .severity_overrides
-            .get(".github/workflows")
-            .copied()
-            .unwrap_or(Severity::Recommended),
-    };
-
-    if workflow_severity != Severity::Optional {
-        max_score += 5;
-    }
-
-    let has_workflows = client.file_exists(owner, repo, ".github/workflows").await;
-
-    if has_workflows {
-        if workflow_severity != Severity::Optional {
-            total_score += 5;
+        if severity == Severity::Optional {
+            message.push_str(" (optional)");
         }
-        checks.push(Check {
-            name: ".github/workflows".to_string(),
-            category: CheckCategory::Structure,
-            severity: workflow_severity,
-            status: CheckStatus::Pass,
-            points: if workflow_severity != Severity::Optional { 5 } else { 0 },
-            max_points: if workflow_severity != Severity::Optional { 5 } else { 0 },
-            message: "GitHub Actions workflows found".to_string(),
-        });
-    } else {
-        let status = match workflow_severity {
-            Severity::Required => {
-                required_passed = false;
-                CheckStatus::Fail
-            }
-            Severity::Recommended => CheckStatus::Warn,
-            Severity::Optional => CheckStatus::Skip,
-        };
+        total_score += points as u32;
 
         checks.push(Check {
-            name: ".github/workflows".to_string(),
-            category: CheckCategory::Structure,
-            severity: workflow_severity,
+            name,
+            category: check.category(),
+            severity,
             status,
-            points: 0,
-            max_points: if workflow_severity != Severity::Optional { 5 } else { 0 },
-            message: "No GitHub Actions workflows".to_string(),
+            points,
+            max_points,
+            message,
+            locations,
         });
     }
 
-    // Check license type
-    if let Ok(repo_info) = client.get_repository(owner, repo).await {
-        let license_severity = match policy {
-            PolicyPack::Minimal => Severity::Recommended,
-            _ => Severity::Required,
-        };
-
-        max_score += 5;
-        if let Some(license) = repo_info.license {
-            let approved_licenses = ["agpl-3.0", "apache-2.0", "mit", "mpl-2.0", "lgpl-3.0"];
-            if approved_licenses.contains(&license.key.as_str()) {
-                total_score += 5;
-                checks.push(Check {
-                    name: "license-type".to_string(),
-                    category: CheckCategory::Governance,
-                    severity: license_severity,
-                    status: CheckStatus::Pass,
-                    points: 5,
-                    max_points: 5,
-                    message: format!("Approved license: {}", license.name),
-                });
-            } else {
-                if license_severity == Severity::Required {
-                    required_passed = false;
-                }
-                checks.push(Check {
-                    name: "license-type".to_string(),
-                    category: CheckCategory::Governance,
-                    severity: license_severity,
-                    status: CheckStatus::Warn,
-                    points: 2,
-                    max_points: 5,
-                    message: format!("Non-standard license: {}", license.name),
-                });
-                total_score += 2;
-            }
-        } else {
-            if license_severity == Severity::Required {
-                required_passed = false;
-            }
-            checks.push(Check {
-                name: "license-type".to_string(),
-                category: CheckCategory::Governance,
-                severity: license_severity,
-                status: CheckStatus::Fail,
-                points: 0,
-                max_points: 5,
-                message: "No license detected".to_string(),
-            });
-        }
-    }
-
     let percentage = if max_score > 0 {
         (total_score as f32 / max_score as f32) * 100.0
     } else {
@@ -549,46 +663,580 @@ pub async fn check_compliance_with_policy(
 
     let summary = if !required_passed {
         format!("RSR {} policy: Required checks failed", policy)
-    } else if percentage >= 90.0 {
+    } else if percentage >= 90.0_f32.max(thresholds.pass) {
         format!("Excellent RSR compliance ({})", policy)
-    } else if percentage >= 70.0 {
+    } else if percentage >= thresholds.pass {
         format!("Good RSR compliance ({}) with minor issues", policy)
-    } else if percentage >= 50.0 {
+    } else if percentage >= thresholds.neutral {
         format!("Partial RSR compliance ({}) - improvements needed", policy)
     } else {
         format!("Poor RSR compliance ({}) - significant work required", policy)
     };
 
+    let commit_sha = source.commit_sha().await.ok();
+    metrics::check_duration(started.elapsed());
+
     Ok(ComplianceReport {
-        owner: owner.to_string(),
-        repo: repo.to_string(),
+        owner: source.owner().to_string(),
+        repo: source.repo().to_string(),
+        commit_sha,
         policy,
+        pack_version: pack.version.clone(),
         score: total_score,
         max_score,
         percentage,
+        thresholds,
+        custom_rules: custom.as_ref().map(CustomPolicy::rules).unwrap_or_default(),
         required_passed,
         checks,
         summary,
+        has_repo_config: ctx.tree.contains(REPO_CONFIG_PATH),
     })
 }
 
+/// Difference between a base and a head report
+///
+/// Used to gate pull requests on what they change rather than on debt that
+/// already exists on the base branch.
+#[derive(Debug, Serialize)]
+pub struct ReportDiff {
+    /// Checks failing on the head that did not fail on the base
+    pub new_failures: Vec<String>,
+    /// Required checks passing on the base that no longer pass on the head,
+    /// including those the head skips or downgrades in its configuration
+    pub removed_required: Vec<String>,
+    /// Checks failing on both, i.e. existing debt
+    pub existing_failures: Vec<String>,
+    /// Checks failing on the base that pass on the head
+    pub fixed: Vec<String>,
+    pub base_percentage: f32,
+    pub head_percentage: f32,
+}
+
+/// How a check came out in the report a diff starts from
+#[derive(Debug, Clone)]
+pub struct BaseCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub severity: Severity,
+}
+
+impl ReportDiff {
+    /// Compare the report of a change's head against its base
+    pub fn between(base: &ComplianceReport, head: &ComplianceReport) -> Self {
+        let checks: Vec<BaseCheck> = base
+            .checks
+            .iter()
+            .map(|c| BaseCheck {
+                name: c.name.clone(),
+                status: c.status,
+                severity: c.severity,
+            })
+            .collect();
+
+        Self::from_base(base.percentage, &checks, head)
+    }
+
+    /// Compare the report of a change's head against the checks of its base,
+    /// e.g. as stored in the history
+    pub fn from_base(base_percentage: f32, base: &[BaseCheck], head: &ComplianceReport) -> Self {
+        let base_status = |name: &str| {
+            base.iter()
+                .find(|c| c.name == name)
+                .map(|c| (c.status, c.severity))
+        };
+
+        let mut diff = Self {
+            new_failures: Vec::new(),
+            removed_required: Vec::new(),
+            existing_failures: Vec::new(),
+            fixed: Vec::new(),
+            base_percentage,
+            head_percentage: head.percentage,
+        };
+
+        for check in &head.checks {
+            match (base_status(&check.name), check.status) {
+                (Some((CheckStatus::Fail, _)), CheckStatus::Fail) => {
+                    diff.existing_failures.push(check.name.clone())
+                }
+                (_, CheckStatus::Fail) => diff.new_failures.push(check.name.clone()),
+                (
+                    Some((CheckStatus::Pass, Severity::Required)),
+                    CheckStatus::Warn | CheckStatus::Skip,
+                ) => diff.removed_required.push(check.name.clone()),
+                (Some((CheckStatus::Fail, _)), CheckStatus::Pass) => {
+                    diff.fixed.push(check.name.clone())
+                }
+                _ => {}
+            }
+        }
+
+        // Required checks dropped from the head report entirely
+        for check in base {
+            if check.status == CheckStatus::Pass
+                && check.severity == Severity::Required
+                && !head.checks.iter().any(|c| c.name == check.name)
+            {
+                diff.removed_required.push(check.name.clone());
+            }
+        }
+
+        diff
+    }
+
+    /// Whether the head score is lower than the base score
+    pub fn score_lowered(&self) -> bool {
+        self.head_percentage < self.base_percentage
+    }
+
+    /// Whether the change introduces any regression
+    pub fn regressed(&self) -> bool {
+        !self.new_failures.is_empty() || !self.removed_required.is_empty() || self.score_lowered()
+    }
+}
+
+/// Check-run conclusion for a report on its own
+///
+/// Fails if required checks didn't pass or the score is below the neutral
+/// threshold, and is neutral between the neutral and pass thresholds.
+pub fn report_conclusion(report: &ComplianceReport) -> &'static str {
+    if !report.required_passed {
+        "failure"
+    } else if report.percentage >= report.thresholds.pass {
+        "success"
+    } else if report.percentage >= report.thresholds.neutral {
+        "neutral"
+    } else {
+        "failure"
+    }
+}
+
 /// Get the policy pack configuration summary
-pub fn policy_summary(policy: PolicyPack) -> &'static str {
+pub fn policy_summary(policy: PolicyPack) -> String {
     match policy {
-        PolicyPack::Minimal => {
-            "Minimal policy: README.adoc and LICENSE.txt required. Other checks optional."
+        PolicyPack::Custom => {
+            "Custom policy: Defined by repository .rsr.toml configuration.".to_string()
+        }
+        pack => policy::current().get(pack).description.clone(),
+    }
+}
+
+/// Human-oriented explanation of a single check
+#[derive(Debug)]
+pub struct CheckExplanation {
+    pub name: String,
+    pub description: String,
+    pub category: CheckCategory,
+    pub points: u8,
+    /// Severity in each built-in policy pack that defines the check
+    pub severities: Vec<(PolicyPack, Severity)>,
+    pub remediation: String,
+}
+
+/// Built-in policy packs, from least to most strict
+pub const BUILTIN_PACKS: [PolicyPack; 4] = [
+    PolicyPack::Minimal,
+    PolicyPack::Standard,
+    PolicyPack::Strict,
+    PolicyPack::Enterprise,
+];
+
+/// Explain a built-in check by the name it has in compliance reports
+pub fn explain_check(name: &str) -> Option<CheckExplanation> {
+    let packs = policy::current();
+    let mut explanation: Option<CheckExplanation> = None;
+
+    for pack in BUILTIN_PACKS {
+        let registry = CheckRegistry::builtin(packs.get(pack));
+        let Some((check, severity)) = registry
+            .get(name)
+            .or_else(|| registry.get(&format!("no-{}", name)))
+        else {
+            continue;
+        };
+
+        explanation
+            .get_or_insert_with(|| CheckExplanation {
+                name: check.id(),
+                description: check.description(),
+                category: check.category(),
+                points: check.points(),
+                severities: Vec::new(),
+                remediation: check.remediation(),
+            })
+            .severities
+            .push((pack, severity));
+    }
+
+    explanation
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::source::tests::MemorySource;
+
+    /// A check with a status and severity, and no flagged locations
+    pub fn check(name: &str, severity: Severity, status: CheckStatus) -> Check {
+        let max_points = 5;
+
+        Check {
+            name: name.to_string(),
+            category: CheckCategory::Documentation,
+            severity,
+            status,
+            points: if status == CheckStatus::Pass {
+                max_points
+            } else {
+                0
+            },
+            max_points,
+            message: format!("{} {:?}", name, status),
+            locations: Vec::new(),
         }
-        PolicyPack::Standard => {
-            "Standard policy: Core files required, security and structure recommended."
+    }
+
+    /// A standard-pack report of `owner/repo` made of `checks`
+    pub fn report(checks: Vec<Check>) -> ComplianceReport {
+        let score = checks.iter().map(|check| u32::from(check.points)).sum();
+        let max_score = checks.iter().map(|check| u32::from(check.max_points)).sum();
+
+        ComplianceReport {
+            owner: "owner".to_string(),
+            repo: "repo".to_string(),
+            commit_sha: Some("abc123".to_string()),
+            policy: PolicyPack::Standard,
+            pack_version: "1".to_string(),
+            score,
+            max_score,
+            percentage: if max_score == 0 {
+                100.0
+            } else {
+                score as f32 * 100.0 / max_score as f32
+            },
+            thresholds: Thresholds::default(),
+            custom_rules: Vec::new(),
+            required_passed: checks.iter().all(|check| check.status != CheckStatus::Fail),
+            checks,
+            summary: String::new(),
+            has_repo_config: false,
         }
-        PolicyPack::Strict => {
-            "Strict policy: Most files required, language policy enforced."
+    }
+
+    /// Name of every check in a diff list
+    fn names(list: &[String]) -> Vec<&str> {
+        list.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn diff_between_base_and_head() {
+        use CheckStatus::*;
+        use Severity::*;
+
+        // (base, head, new failure, removed required, existing failure, fixed)
+        let cases = [
+            (
+                Some((Pass, Required)),
+                (Pass, Required),
+                false,
+                false,
+                false,
+                false,
+            ),
+            (
+                Some((Pass, Required)),
+                (Fail, Required),
+                true,
+                false,
+                false,
+                false,
+            ),
+            (
+                Some((Pass, Required)),
+                (Warn, Recommended),
+                false,
+                true,
+                false,
+                false,
+            ),
+            (
+                Some((Pass, Required)),
+                (Skip, Optional),
+                false,
+                true,
+                false,
+                false,
+            ),
+            (
+                Some((Pass, Recommended)),
+                (Warn, Recommended),
+                false,
+                false,
+                false,
+                false,
+            ),
+            (
+                Some((Pass, Recommended)),
+                (Skip, Optional),
+                false,
+                false,
+                false,
+                false,
+            ),
+            (
+                Some((Fail, Required)),
+                (Fail, Required),
+                false,
+                false,
+                true,
+                false,
+            ),
+            (
+                Some((Fail, Required)),
+                (Pass, Required),
+                false,
+                false,
+                false,
+                true,
+            ),
+            (
+                Some((Warn, Recommended)),
+                (Fail, Required),
+                true,
+                false,
+                false,
+                false,
+            ),
+            (None, (Fail, Required), true, false, false, false),
+            (None, (Pass, Required), false, false, false, false),
+        ];
+
+        for (base_check, (status, severity), new, removed, existing, fixed) in cases {
+            let base = report(
+                base_check
+                    .map(|(status, severity)| check("SECURITY.md", severity, status))
+                    .into_iter()
+                    .collect(),
+            );
+            let head = report(vec![check("SECURITY.md", severity, status)]);
+            let diff = ReportDiff::between(&base, &head);
+            let expect = |flag: bool| if flag { vec!["SECURITY.md"] } else { vec![] };
+            let case = format!("{:?} -> {:?}", base_check, (status, severity));
+
+            assert_eq!(names(&diff.new_failures), expect(new), "{}", case);
+            assert_eq!(names(&diff.removed_required), expect(removed), "{}", case);
+            assert_eq!(names(&diff.existing_failures), expect(existing), "{}", case);
+            assert_eq!(names(&diff.fixed), expect(fixed), "{}", case);
         }
-        PolicyPack::Enterprise => {
-            "Enterprise policy: All checks required, full compliance mandatory."
+    }
+
+    #[test]
+    fn diff_counts_dropped_required_checks() {
+        let base = report(vec![
+            check("README.adoc", Severity::Required, CheckStatus::Pass),
+            check("STATE.scm", Severity::Recommended, CheckStatus::Pass),
+        ]);
+        let head = report(Vec::new());
+
+        let diff = ReportDiff::between(&base, &head);
+        assert_eq!(names(&diff.removed_required), ["README.adoc"]);
+        assert!(diff.regressed());
+    }
+
+    #[test]
+    fn skipping_a_required_check_regresses() {
+        let base = report(vec![
+            check("README.adoc", Severity::Required, CheckStatus::Pass),
+            check("SECURITY.md", Severity::Required, CheckStatus::Fail),
+        ]);
+        let head = report(vec![
+            check("README.adoc", Severity::Optional, CheckStatus::Skip),
+            check("SECURITY.md", Severity::Required, CheckStatus::Fail),
+        ]);
+
+        assert!(ReportDiff::between(&base, &head).regressed());
+    }
+
+    fn config(toml: &str) -> RepoConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn locked_checks_keep_the_org_severity_as_a_floor() {
+        let locked = LockedSettings {
+            checks: vec!["SECURITY.md".to_string(), "no-*.ts".to_string()],
+            policy: false,
+        };
+
+        // (org, repo, SECURITY.md, no-*.ts)
+        let cases = [
+            (
+                "policy = \"strict\"",
+                "policy = \"minimal\"",
+                Some(Severity::Required),
+                None,
+            ),
+            (
+                "policy = \"strict\"",
+                "policy = \"enterprise\"",
+                Some(Severity::Required),
+                None,
+            ),
+            (
+                "policy = \"minimal\"\n[severity_overrides]\n\"SECURITY.md\" = \"recommended\"",
+                "policy = \"minimal\"",
+                Some(Severity::Recommended),
+                None,
+            ),
+            (
+                "policy = \"minimal\"",
+                "policy = \"strict\"",
+                Some(Severity::Required),
+                None,
+            ),
+            (
+                "policy = \"standard\"\nban = [\"*.ts\"]",
+                "policy = \"minimal\"",
+                Some(Severity::Recommended),
+                Some(Severity::Required),
+            ),
+        ];
+
+        for (org, repo, security, typescript) in cases {
+            let org = config(org);
+            let mut repo = config(repo);
+            enforce_locked_severities(&mut repo, &org, &locked);
+
+            assert_eq!(repo.severity_of("SECURITY.md"), security, "{:?}", org);
+            assert_eq!(repo.severity_of("no-*.ts"), typescript, "{:?}", org);
         }
-        PolicyPack::Custom => {
-            "Custom policy: Defined by repository .rsr.toml configuration."
+    }
+
+    #[tokio::test]
+    async fn invalid_repo_config_falls_back_to_the_org_config() {
+        let org = "policy = \"strict\"\n[locked]\npolicy = true\nchecks = [\"SECURITY.md\"]";
+
+        // (.rsr.toml, policy, SECURITY.md, skipped checks)
+        let cases = [
+            (
+                "skip = 1",
+                PolicyPack::Strict,
+                Some(Severity::Required),
+                vec![],
+            ),
+            (
+                "policy = \"minimal\"\nskip = 1",
+                PolicyPack::Strict,
+                Some(Severity::Required),
+                vec![],
+            ),
+            (
+                "not toml at all [",
+                PolicyPack::Strict,
+                Some(Severity::Required),
+                vec![],
+            ),
+            (
+                "policy = \"minimal\"\nskip = [\"META.scm\"]",
+                PolicyPack::Strict,
+                Some(Severity::Required),
+                vec!["META.scm"],
+            ),
+        ];
+
+        for (repo, policy, security, skip) in cases {
+            let mut source = MemorySource::with_files(&[(REPO_CONFIG_PATH, repo)]);
+            source.org_config = Some(org.to_string());
+            let config = load_repo_config(&source).await;
+
+            assert_eq!(config.policy, policy, "{}", repo);
+            assert_eq!(config.severity_of("SECURITY.md"), security, "{}", repo);
+            assert_eq!(config.skip, skip, "{}", repo);
         }
     }
+
+    #[tokio::test]
+    async fn required_and_banned_paths_are_checked_as_required() {
+        let source = MemorySource::with_files(&[
+            ("docs/ARCHITECTURE.md", "# Architecture"),
+            ("src/app.ts", "export {}"),
+        ]);
+        let repo_config = config(
+            "policy = \"minimal\"\nrequire = [\"docs/ARCHITECTURE.md\", \"CHANGELOG.md\"]\nban = [\"*.ts\"]",
+        );
+
+        let report = check_compliance_with_policy(&source, &repo_config)
+            .await
+            .unwrap();
+        let check = |name: &str| {
+            report
+                .checks
+                .iter()
+                .find(|check| check.name == name)
+                .unwrap_or_else(|| panic!("no {} check", name))
+        };
+
+        assert_eq!(check("docs/ARCHITECTURE.md").status, CheckStatus::Pass);
+        assert_eq!(check("CHANGELOG.md").status, CheckStatus::Fail);
+        assert_eq!(check("CHANGELOG.md").severity, Severity::Required);
+        assert_eq!(check("no-*.ts").status, CheckStatus::Fail);
+        assert_eq!(check("no-*.ts").locations, [Location::file("src/app.ts")]);
+        assert!(!report.required_passed);
+    }
+
+    #[tokio::test]
+    async fn custom_packs_set_severities_points_checks_and_thresholds() {
+        let source =
+            MemorySource::with_files(&[("SECURITY.md", "# Security"), ("tools/setup.exe", "")]);
+        let repo_config = config(
+            r#"
+policy = "custom"
+
+[custom]
+extends = "strict"
+pass_threshold = 80
+neutral_threshold = 60
+
+[custom.severities]
+"CODE_OF_CONDUCT.md" = "optional"
+
+[custom.points]
+"SECURITY.md" = 10
+
+[[custom.checks]]
+require = "docs/ARCHITECTURE.md"
+points = 4
+
+[[custom.checks]]
+ban = "*.exe"
+severity = "recommended"
+"#,
+        );
+
+        let report = check_compliance_with_policy(&source, &repo_config)
+            .await
+            .unwrap();
+        let check = |name: &str| {
+            report
+                .checks
+                .iter()
+                .find(|check| check.name == name)
+                .unwrap_or_else(|| panic!("no {} check", name))
+        };
+
+        assert_eq!(report.policy, PolicyPack::Custom);
+        assert_eq!(
+            (report.thresholds.pass, report.thresholds.neutral),
+            (80.0, 60.0)
+        );
+        assert_eq!(check("CODE_OF_CONDUCT.md").severity, Severity::Optional);
+        assert_eq!(check("SECURITY.md").status, CheckStatus::Pass);
+        assert_eq!(check("SECURITY.md").points, 10);
+        assert_eq!(check("docs/ARCHITECTURE.md").severity, Severity::Required);
+        assert_eq!(check("docs/ARCHITECTURE.md").max_points, 4);
+        assert_eq!(check("no-*.exe").severity, Severity::Recommended);
+        assert_eq!(check("no-*.exe").status, CheckStatus::Warn);
+        assert_eq!(report.custom_rules.len(), 7);
+    }
 }
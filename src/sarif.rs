@@ -0,0 +1,324 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! SARIF output
+//!
+//! Converts a compliance report into a SARIF 2.1.0 log, so findings can be
+//! uploaded to GitHub code scanning or read by other SARIF consumers. Every
+//! check of the report is a rule and every failing or warning check a result.
+
+use serde::Serialize;
+
+use crate::rsr::{self, CheckStatus, ComplianceReport, Severity};
+
+/// Schema of the logs produced
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// SARIF version of the logs produced
+const VERSION: &str = "2.1.0";
+
+/// Top-level SARIF document
+#[derive(Debug, Serialize)]
+pub struct SarifLog {
+    #[serde(rename = "$schema")]
+    pub schema: &'static str,
+    pub version: &'static str,
+    pub runs: Vec<Run>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Run {
+    pub tool: Tool,
+    pub results: Vec<SarifResult>,
+    pub properties: RunProperties,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Tool {
+    pub driver: Driver,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Driver {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub information_uri: &'static str,
+    pub rules: Vec<Rule>,
+}
+
+/// A check, as a SARIF reporting descriptor
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    pub id: String,
+    pub short_description: Message,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub help: Option<Message>,
+    pub default_configuration: RuleConfiguration,
+    pub properties: RuleProperties,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuleConfiguration {
+    pub level: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuleProperties {
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Message {
+    pub text: String,
+}
+
+/// A failing or warning check
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifResult {
+    pub rule_id: String,
+    pub rule_index: usize,
+    pub level: &'static str,
+    pub message: Message,
+    pub locations: Vec<Location>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Location {
+    pub physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhysicalLocation {
+    pub artifact_location: ArtifactLocation,
+    pub region: Region,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactLocation {
+    pub uri: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Region {
+    pub start_line: u32,
+}
+
+/// What the run checked, beyond what SARIF itself describes
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunProperties {
+    pub repository: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_sha: Option<String>,
+    pub policy: String,
+    pub pack_version: String,
+    pub score: u32,
+    pub max_score: u32,
+    pub percentage: f32,
+    pub required_passed: bool,
+}
+
+/// SARIF level of a check's severity
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Required => "error",
+        Severity::Recommended => "warning",
+        Severity::Optional => "note",
+    }
+}
+
+/// License file the policy packs require, where a missing license is reported
+const LICENSE_PATH: &str = "LICENSE.txt";
+
+/// Where a check's findings are reported
+///
+/// Checks that flag files point at each of them. Other checks, such as a
+/// missing required file, point at the repository's `.rsr.toml` if it has
+/// one, which sets the policy they come from, and otherwise at the file they
+/// are about; code scanning needs a location for every result.
+fn locations(check: &rsr::Check, report: &ComplianceReport) -> Vec<Location> {
+    let locations = if !check.locations.is_empty() {
+        check.locations.clone()
+    } else if report.has_repo_config {
+        vec![rsr::Location::file(rsr::REPO_CONFIG_PATH)]
+    } else if check.name == "license-type" {
+        vec![rsr::Location::file(LICENSE_PATH)]
+    } else {
+        vec![rsr::Location::file(check.name.as_str())]
+    };
+
+    locations
+        .into_iter()
+        .map(|location| Location {
+            physical_location: PhysicalLocation {
+                artifact_location: ArtifactLocation { uri: location.path },
+                region: Region {
+                    start_line: location.line.unwrap_or(1),
+                },
+            },
+        })
+        .collect()
+}
+
+/// Convert a report into a SARIF log with a single run
+pub fn from_report(report: &ComplianceReport) -> SarifLog {
+    let rules = report
+        .checks
+        .iter()
+        .map(|check| {
+            let explanation = rsr::explain_check(&check.name);
+            let category = serde_json::to_value(check.category)
+                .ok()
+                .and_then(|value| value.as_str().map(str::to_string))
+                .unwrap_or_default();
+
+            Rule {
+                id: check.name.clone(),
+                short_description: Message {
+                    text: explanation
+                        .as_ref()
+                        .map_or_else(|| check.name.clone(), |e| e.description.clone()),
+                },
+                help: explanation.map(|e| Message {
+                    text: e.remediation,
+                }),
+                default_configuration: RuleConfiguration {
+                    level: level(check.severity),
+                },
+                properties: RuleProperties {
+                    tags: vec!["rsr".to_string(), category],
+                },
+            }
+        })
+        .collect();
+
+    let results = report
+        .checks
+        .iter()
+        .enumerate()
+        .filter(|(_, check)| matches!(check.status, CheckStatus::Fail | CheckStatus::Warn))
+        .map(|(index, check)| SarifResult {
+            rule_id: check.name.clone(),
+            rule_index: index,
+            level: level(check.severity),
+            message: Message {
+                text: check.message.clone(),
+            },
+            locations: locations(check, report),
+        })
+        .collect();
+
+    SarifLog {
+        schema: SCHEMA,
+        version: VERSION,
+        runs: vec![Run {
+            tool: Tool {
+                driver: Driver {
+                    name: "rhodibot",
+                    version: env!("CARGO_PKG_VERSION"),
+                    information_uri: env!("CARGO_PKG_HOMEPAGE"),
+                    rules,
+                },
+            },
+            results,
+            properties: RunProperties {
+                repository: format!("{}/{}", report.owner, report.repo),
+                commit_sha: report.commit_sha.clone(),
+                policy: report.policy.to_string(),
+                pack_version: report.pack_version.clone(),
+                score: report.score,
+                max_score: report.max_score,
+                percentage: report.percentage,
+                required_passed: report.required_passed,
+            },
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsr::tests::{check, report};
+
+    /// Rule and `path:line` locations of every result
+    fn results(log: &SarifLog) -> Vec<(&str, Vec<String>)> {
+        log.runs[0]
+            .results
+            .iter()
+            .map(|result| {
+                let locations = result
+                    .locations
+                    .iter()
+                    .map(|location| {
+                        let physical = &location.physical_location;
+                        format!(
+                            "{}:{}",
+                            physical.artifact_location.uri, physical.region.start_line
+                        )
+                    })
+                    .collect();
+                (result.rule_id.as_str(), locations)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn results_point_at_flagged_files_the_repo_config_or_their_file() {
+        let mut banned = check("no-*.ts", Severity::Recommended, CheckStatus::Warn);
+        banned.locations = vec![
+            rsr::Location::file("src/app.ts"),
+            rsr::Location {
+                path: "src/lib.ts".to_string(),
+                line: Some(3),
+            },
+        ];
+        let mut report = report(vec![
+            check("README.md", Severity::Required, CheckStatus::Pass),
+            check("SECURITY.md", Severity::Required, CheckStatus::Fail),
+            check("license-type", Severity::Required, CheckStatus::Fail),
+            banned,
+            check("META.scm", Severity::Optional, CheckStatus::Skip),
+        ]);
+
+        let log = from_report(&report);
+        assert_eq!(log.runs[0].tool.driver.rules.len(), 5);
+        let levels: Vec<(usize, &str)> = log.runs[0]
+            .results
+            .iter()
+            .map(|result| (result.rule_index, result.level))
+            .collect();
+        assert_eq!(levels, [(1, "error"), (2, "error"), (3, "warning")]);
+        assert_eq!(
+            results(&log),
+            [
+                ("SECURITY.md", vec!["SECURITY.md:1".to_string()]),
+                ("license-type", vec!["LICENSE.txt:1".to_string()]),
+                (
+                    "no-*.ts",
+                    vec!["src/app.ts:1".to_string(), "src/lib.ts:3".to_string()]
+                ),
+            ]
+        );
+
+        report.has_repo_config = true;
+        let log = from_report(&report);
+        assert_eq!(
+            results(&log),
+            [
+                ("SECURITY.md", vec![".rsr.toml:1".to_string()]),
+                ("license-type", vec![".rsr.toml:1".to_string()]),
+                (
+                    "no-*.ts",
+                    vec!["src/app.ts:1".to_string(), "src/lib.ts:3".to_string()]
+                ),
+            ]
+        );
+    }
+}
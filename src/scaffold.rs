@@ -0,0 +1,288 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! Auto-fix pull requests that scaffold missing RSR files
+//!
+//! Missing required files are rendered from the `scaffold/` templates filled
+//! with repository metadata, committed to a single bot branch and proposed in one
+//! pull request. While that pull request is open, later runs rebuild the
+//! branch on the current default branch and update the pull request instead
+//! of opening another. The branch is left alone once someone else commits to
+//! it, and a pull request closed by a maintainer is not opened again unless
+//! explicitly requested.
+
+use anyhow::Result;
+use serde::Serialize;
+use tracing::info;
+
+use crate::github::{CreatePull, GitHubClient, NewTreeFile, PullRequest, Repository};
+use crate::rsr::{CheckStatus, ComplianceReport};
+use crate::source::{GitHubSource, RepoSource};
+use crate::templates;
+
+/// Branch the scaffolded files are committed to
+pub const SCAFFOLD_BRANCH: &str = "rhodibot/rsr-scaffold";
+
+/// Title of the scaffold pull request
+const PR_TITLE: &str = "[Rhodibot] Add missing RSR files";
+
+/// First line of the scaffold commit, which tells rhodibot's commits apart
+const COMMIT_SUBJECT: &str = "Add missing RSR files";
+
+/// Files rhodibot can scaffold, with the template each is rendered from
+const SCAFFOLD_FILES: &[(&str, &str)] = &[
+    ("README.adoc", "scaffold/README.adoc"),
+    ("SECURITY.md", "scaffold/SECURITY.md"),
+    ("CONTRIBUTING.md", "scaffold/CONTRIBUTING.md"),
+    ("CODE_OF_CONDUCT.md", "scaffold/CODE_OF_CONDUCT.md"),
+    (".claude/CLAUDE.md", "scaffold/CLAUDE.md"),
+    ("STATE.scm", "scaffold/STATE.scm"),
+    ("META.scm", "scaffold/META.scm"),
+    ("ECOSYSTEM.scm", "scaffold/ECOSYSTEM.scm"),
+];
+
+/// Repository metadata available to scaffold templates
+#[derive(Debug, Serialize)]
+pub struct RepoMetadata {
+    pub name: String,
+    pub owner: String,
+    pub full_name: String,
+    pub description: String,
+    pub default_branch: String,
+    /// SPDX identifier of the repository license (`NOASSERTION` if unknown)
+    pub license_spdx: String,
+    pub license_name: String,
+    /// Today's date (YYYY-MM-DD)
+    pub date: String,
+}
+
+impl RepoMetadata {
+    pub fn from_repository(owner: &str, repository: &Repository) -> Self {
+        let license = repository.license.as_ref();
+
+        Self {
+            name: repository.name.clone(),
+            owner: owner.to_string(),
+            full_name: repository.full_name.clone(),
+            description: repository.description.clone().unwrap_or_default(),
+            default_branch: repository.default_branch.clone(),
+            license_spdx: license
+                .and_then(|l| l.spdx_id.clone())
+                .unwrap_or_else(|| "NOASSERTION".to_string()),
+            license_name: license
+                .map(|l| l.name.clone())
+                .unwrap_or_else(|| "the project's license".to_string()),
+            date: chrono::Utc::now().format("%Y-%m-%d").to_string(),
+        }
+    }
+}
+
+/// Paths of missing files that rhodibot has a template for
+///
+/// Every scaffoldable check is a file-presence check, so both failed
+/// (required) and warning (recommended) ones are missing files.
+pub fn missing_files(report: &ComplianceReport) -> Vec<&'static str> {
+    report
+        .checks
+        .iter()
+        .filter(|check| matches!(check.status, CheckStatus::Fail | CheckStatus::Warn))
+        .filter_map(|check| {
+            SCAFFOLD_FILES
+                .iter()
+                .find(|(path, _)| *path == check.name)
+                .map(|(path, _)| *path)
+        })
+        .collect()
+}
+
+/// The `missing` files that are still absent from `source`
+///
+/// A report can be older than the default branch, or describe a pull
+/// request head, so its missing files are checked again before scaffolding.
+pub async fn still_missing(
+    source: &dyn RepoSource,
+    missing: Vec<&'static str>,
+) -> Result<Vec<&'static str>> {
+    let mut absent = Vec::new();
+    for path in missing {
+        if !source.file_exists(path).await? {
+            absent.push(path);
+        }
+    }
+    Ok(absent)
+}
+
+/// Render the scaffold of one file
+pub fn render(path: &str, metadata: &RepoMetadata) -> Result<String> {
+    let (_, template) = SCAFFOLD_FILES
+        .iter()
+        .find(|(file, _)| *file == path)
+        .ok_or_else(|| anyhow::anyhow!("No scaffold template for {}", path))?;
+
+    templates::render(template, metadata)
+}
+
+/// Open or refresh the scaffold pull request for a report's missing files
+///
+/// Only files still missing on the default branch are scaffolded. Returns
+/// the pull request, or `None` if there is nothing to scaffold or a
+/// maintainer closed the last scaffold pull request. `requested` (the check
+/// run's button) opens a new pull request even then. The branch and pull
+/// request are only updated when the scaffolded tree changed, and never over
+/// commits rhodibot did not make.
+pub async fn open_or_update_pr(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    report: &ComplianceReport,
+    requested: bool,
+) -> Result<Option<PullRequest>> {
+    let missing = missing_files(report);
+    if missing.is_empty() {
+        return Ok(None);
+    }
+
+    let mut pulls = client
+        .list_pulls_from(owner, repo, SCAFFOLD_BRANCH, "all")
+        .await?;
+    if let Some(latest) = pulls.first()
+        && latest.state == "closed"
+        && latest.merged_at.is_none()
+        && !requested
+    {
+        info!(
+            "Scaffold pull request #{} of {}/{} was closed, not opening another",
+            latest.number, owner, repo
+        );
+        return Ok(None);
+    }
+    let open = pulls
+        .iter()
+        .position(|pull| pull.state == "open")
+        .map(|index| pulls.swap_remove(index));
+
+    let repository = client.get_repository(owner, repo).await?;
+    let metadata = RepoMetadata::from_repository(owner, &repository);
+
+    // Rebuild the branch on the current default branch head
+    let base_sha = client
+        .get_commit_sha(owner, repo, &repository.default_branch)
+        .await?;
+    let base_source = GitHubSource::new(client, owner, repo, Some(&base_sha));
+    let missing = still_missing(&base_source, missing).await?;
+    if missing.is_empty() {
+        info!(
+            "Missing files of {}/{} exist on {}, nothing to scaffold",
+            owner, repo, repository.default_branch
+        );
+        return Ok(None);
+    }
+
+    let files = missing
+        .iter()
+        .map(|path| Ok(NewTreeFile::text(path, render(path, &metadata)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let base = client.get_git_commit(owner, repo, &base_sha).await?;
+    let tree = client
+        .create_tree(owner, repo, &base.tree.sha, &files)
+        .await?;
+    let message = format!("{}\n\nScaffolds {}.", COMMIT_SUBJECT, missing.join(", "));
+
+    let branch_ref = format!("heads/{}", SCAFFOLD_BRANCH);
+    let branch = match client.get_ref(owner, repo, &branch_ref).await? {
+        Some(branch) => Some(client.get_git_commit(owner, repo, &branch.object.sha).await?),
+        None => None,
+    };
+
+    match branch {
+        Some(head) if head.tree.sha == tree.sha => {
+            if let Some(pull) = open {
+                info!("Scaffold pull request #{} is up to date", pull.number);
+                return Ok(Some(pull));
+            }
+        }
+        Some(head) if head.message.lines().next() != Some(COMMIT_SUBJECT) => {
+            info!(
+                "{} of {}/{} has commits rhodibot did not make, leaving it",
+                SCAFFOLD_BRANCH, owner, repo
+            );
+            return Ok(open);
+        }
+        Some(_) => {
+            let commit = client
+                .create_commit(owner, repo, &message, &tree.sha, &[&base_sha])
+                .await?;
+            client
+                .update_ref(owner, repo, &branch_ref, &commit.sha, true)
+                .await?;
+        }
+        None => {
+            let commit = client
+                .create_commit(owner, repo, &message, &tree.sha, &[&base_sha])
+                .await?;
+            client
+                .create_ref(owner, repo, &format!("refs/{}", branch_ref), &commit.sha)
+                .await?;
+        }
+    }
+
+    let body = templates::render(
+        "scaffold-pr.md",
+        minijinja::context! { missing, report, metadata },
+    )?;
+
+    let pull = match open {
+        Some(pull) => {
+            client
+                .update_pull(owner, repo, pull.number, PR_TITLE, &body)
+                .await?
+        }
+        None => {
+            let pull = CreatePull {
+                title: PR_TITLE.to_string(),
+                head: SCAFFOLD_BRANCH.to_string(),
+                base: repository.default_branch.clone(),
+                body,
+            };
+            client.create_pull(owner, repo, &pull).await?
+        }
+    };
+
+    Ok(Some(pull))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsr::Severity;
+    use crate::rsr::tests::{check, report};
+    use crate::source::tests::MemorySource;
+
+    #[test]
+    fn missing_files_are_failed_and_warning_scaffoldable_checks() {
+        let report = report(vec![
+            check("README.adoc", Severity::Required, CheckStatus::Fail),
+            check("SECURITY.md", Severity::Required, CheckStatus::Pass),
+            check("CONTRIBUTING.md", Severity::Recommended, CheckStatus::Warn),
+            check("STATE.scm", Severity::Optional, CheckStatus::Skip),
+            check("LICENSE.txt", Severity::Required, CheckStatus::Fail),
+            check("no-*.ts", Severity::Required, CheckStatus::Fail),
+        ]);
+
+        assert_eq!(missing_files(&report), ["README.adoc", "CONTRIBUTING.md"]);
+    }
+
+    #[tokio::test]
+    async fn files_added_since_the_report_are_not_scaffolded() {
+        let default_branch = MemorySource::with_files(&[
+            ("README.adoc", "= Repo"),
+            (".claude/CLAUDE.md", "# Notes"),
+        ]);
+
+        let missing = vec!["README.adoc", "SECURITY.md", ".claude/CLAUDE.md"];
+        assert_eq!(
+            still_missing(&default_branch, missing).await.unwrap(),
+            ["SECURITY.md"]
+        );
+    }
+}
@@ -0,0 +1,660 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! Repository source abstraction
+//!
+//! The compliance engine reads repositories through [`RepoSource`], so the
+//! same checks run against the GitHub API or against a local checkout or bare
+//! repository read through gix, with no network access.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Instant;
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+use crate::github::{GitHubClient, GitTreeEntry, Repository};
+use crate::rsr::ORG_CONFIG_PATH;
+
+/// A repository the compliance engine can inspect
+#[async_trait]
+pub trait RepoSource: Send + Sync {
+    /// Repository owner (user or organization)
+    fn owner(&self) -> &str;
+
+    /// Repository name
+    fn repo(&self) -> &str;
+
+    /// SHA of the commit being inspected
+    async fn commit_sha(&self) -> Result<String>;
+
+    /// Check if a file or directory exists
+    async fn file_exists(&self, path: &str) -> Result<bool>;
+
+    /// Read a file as UTF-8 text
+    async fn read_file(&self, path: &str) -> Result<String>;
+
+    /// Detect the repository license, `Ok(None)` if there is none
+    async fn license(&self) -> Result<Option<LicenseInfo>>;
+
+    /// List every file and directory in the repository, recursively
+    async fn list_tree(&self) -> Result<Vec<TreeEntry>>;
+
+    /// Org-wide RSR configuration the repository inherits, if any
+    async fn org_config(&self) -> Option<String> {
+        None
+    }
+}
+
+/// File or directory in a repository tree
+#[derive(Debug, Clone)]
+pub struct TreeEntry {
+    /// Path relative to the repository root, `/`-separated
+    pub path: String,
+    pub is_dir: bool,
+}
+
+/// License detected for a repository
+#[derive(Debug, Clone)]
+pub struct LicenseInfo {
+    /// Lowercase license key as used by GitHub (e.g. `mpl-2.0`)
+    pub key: String,
+    /// Human-readable license name
+    pub name: String,
+    /// File the license was detected in
+    pub path: String,
+}
+
+/// Index of every path in a repository tree at one commit
+#[derive(Debug, Default)]
+pub struct TreeIndex {
+    /// Path to whether it is a directory
+    entries: BTreeMap<String, bool>,
+}
+
+impl TreeIndex {
+    /// Check if a file or directory exists
+    pub fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path.trim_end_matches('/'))
+    }
+
+    /// Every entry in the tree
+    pub fn entries(&self) -> Vec<TreeEntry> {
+        self.entries
+            .iter()
+            .map(|(path, &is_dir)| TreeEntry {
+                path: path.clone(),
+                is_dir,
+            })
+            .collect()
+    }
+}
+
+impl FromIterator<TreeEntry> for TreeIndex {
+    fn from_iter<I: IntoIterator<Item = TreeEntry>>(iter: I) -> Self {
+        Self {
+            entries: iter
+                .into_iter()
+                .map(|entry| (entry.path, entry.is_dir))
+                .collect(),
+        }
+    }
+}
+
+/// Maximum number of commit trees kept in [`TREE_CACHE`]
+const TREE_CACHE_CAPACITY: usize = 256;
+
+/// Tree indexes keyed by `owner/repo@sha`, shared across reports
+///
+/// When full, the least recently used tree makes room for a new one.
+static TREE_CACHE: LazyLock<Mutex<HashMap<String, CachedTree>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A cached tree index and when a report last used it
+struct CachedTree {
+    index: Arc<TreeIndex>,
+    used: Instant,
+}
+
+/// Drop the least recently used trees to fit a new one
+fn make_room(cache: &mut HashMap<String, CachedTree>, max: usize) {
+    while cache.len() >= max {
+        let Some(oldest) = cache
+            .iter()
+            .min_by_key(|(_, cached)| cached.used)
+            .map(|(key, _)| key.clone())
+        else {
+            break;
+        };
+        cache.remove(&oldest);
+    }
+}
+
+/// Repository read through the GitHub API
+///
+/// The ref is resolved to a commit SHA once, and every read is pinned to that
+/// commit. The tree is fetched once per commit and every existence check is
+/// answered from the in-memory [`TreeIndex`].
+pub struct GitHubSource<'a> {
+    client: &'a GitHubClient,
+    owner: String,
+    repo: String,
+    /// Branch, tag or SHA to inspect, the default branch if `None`
+    git_ref: Option<String>,
+    repo_info: OnceCell<Repository>,
+    sha: OnceCell<String>,
+    tree: OnceCell<Arc<TreeIndex>>,
+}
+
+impl<'a> GitHubSource<'a> {
+    /// Read a repository at `git_ref` (defaults to the default branch)
+    pub fn new(client: &'a GitHubClient, owner: &str, repo: &str, git_ref: Option<&str>) -> Self {
+        Self {
+            client,
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            git_ref: git_ref.map(str::to_string),
+            repo_info: OnceCell::new(),
+            sha: OnceCell::new(),
+            tree: OnceCell::new(),
+        }
+    }
+
+    /// Repository metadata, fetched once
+    async fn repo_info(&self) -> Result<&Repository> {
+        self.repo_info
+            .get_or_try_init(|| self.client.get_repository(&self.owner, &self.repo))
+            .await
+    }
+
+    /// Commit SHA the ref resolves to, resolved once
+    async fn sha(&self) -> Result<&str> {
+        let sha = self
+            .sha
+            .get_or_try_init(|| async {
+                let git_ref = match &self.git_ref {
+                    Some(git_ref) => git_ref,
+                    None => &self.repo_info().await?.default_branch,
+                };
+                self.client
+                    .get_commit_sha(&self.owner, &self.repo, git_ref)
+                    .await
+            })
+            .await?;
+
+        Ok(sha)
+    }
+
+    /// Tree index of the inspected commit, fetched once per commit
+    async fn tree(&self) -> Result<&Arc<TreeIndex>> {
+        self.tree
+            .get_or_try_init(|| async {
+                let sha = self.sha().await?;
+                let key = format!("{}/{}@{}", self.owner, self.repo, sha);
+
+                if let Some(cached) = TREE_CACHE
+                    .lock()
+                    .expect("tree cache poisoned")
+                    .get_mut(&key)
+                {
+                    cached.used = Instant::now();
+                    return Ok(cached.index.clone());
+                }
+
+                let index = Arc::new(self.fetch_tree(sha).await?);
+
+                let mut cache = TREE_CACHE.lock().expect("tree cache poisoned");
+                if !cache.contains_key(&key) {
+                    make_room(&mut cache, TREE_CACHE_CAPACITY);
+                }
+                cache.insert(
+                    key,
+                    CachedTree {
+                        index: index.clone(),
+                        used: Instant::now(),
+                    },
+                );
+
+                Ok(index)
+            })
+            .await
+    }
+
+    /// Fetch the full tree of a commit
+    ///
+    /// Uses a single recursive request, falling back to walking subtrees one
+    /// level at a time when GitHub truncates the recursive listing.
+    async fn fetch_tree(&self, sha: &str) -> Result<TreeIndex> {
+        let tree = self
+            .client
+            .get_tree(&self.owner, &self.repo, sha, true)
+            .await?;
+
+        if !tree.truncated {
+            return Ok(tree.tree.into_iter().map(TreeEntry::from).collect());
+        }
+
+        debug!(
+            "Recursive tree for {}/{} truncated, walking subtrees",
+            self.owner, self.repo
+        );
+
+        let mut entries = Vec::new();
+        let mut pending = vec![(String::new(), sha.to_string())];
+
+        while let Some((prefix, tree_sha)) = pending.pop() {
+            let level = self
+                .client
+                .get_tree(&self.owner, &self.repo, &tree_sha, false)
+                .await?;
+
+            for entry in level.tree {
+                let path = format!("{}{}", prefix, entry.path);
+                if entry.entry_type == "tree" {
+                    pending.push((format!("{}/", path), entry.sha.clone()));
+                }
+                entries.push(TreeEntry {
+                    is_dir: entry.entry_type == "tree",
+                    path,
+                });
+            }
+        }
+
+        Ok(entries.into_iter().collect())
+    }
+}
+
+impl From<GitTreeEntry> for TreeEntry {
+    fn from(entry: GitTreeEntry) -> Self {
+        Self {
+            is_dir: entry.entry_type == "tree",
+            path: entry.path,
+        }
+    }
+}
+
+#[async_trait]
+impl RepoSource for GitHubSource<'_> {
+    fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    fn repo(&self) -> &str {
+        &self.repo
+    }
+
+    async fn commit_sha(&self) -> Result<String> {
+        Ok(self.sha().await?.to_string())
+    }
+
+    async fn file_exists(&self, path: &str) -> Result<bool> {
+        Ok(self.tree().await?.contains(path))
+    }
+
+    async fn read_file(&self, path: &str) -> Result<String> {
+        // Avoid a request for files the tree says are absent
+        if !self.tree().await?.contains(path) {
+            anyhow::bail!("File not found: {}", path);
+        }
+
+        let sha = self.sha().await?;
+        self.client
+            .get_file_content(&self.owner, &self.repo, path, Some(sha))
+            .await
+    }
+
+    async fn license(&self) -> Result<Option<LicenseInfo>> {
+        let sha = self.sha().await?;
+        let content = self
+            .client
+            .get_license(&self.owner, &self.repo, Some(sha))
+            .await?;
+
+        Ok(content.and_then(|content| {
+            content.license.map(|license| LicenseInfo {
+                key: license.key,
+                name: license.name,
+                path: content.path,
+            })
+        }))
+    }
+
+    async fn list_tree(&self) -> Result<Vec<TreeEntry>> {
+        Ok(self.tree().await?.entries())
+    }
+
+    async fn org_config(&self) -> Option<String> {
+        // Org-wide settings live on the default branch of the `.github` repository
+        self.client
+            .get_file_content(&self.owner, ".github", ORG_CONFIG_PATH, None)
+            .await
+            .inspect_err(|e| debug!("No org config for {}: {}", self.owner, e))
+            .ok()
+    }
+}
+
+/// Repository read from a local checkout or bare repository via gix
+pub struct LocalSource {
+    repository: gix::ThreadSafeRepository,
+    owner: String,
+    repo: String,
+    /// Commit the requested revision resolves to
+    commit: gix::ObjectId,
+    /// Every path in the tree at the requested revision
+    entries: BTreeMap<String, LocalEntry>,
+}
+
+/// Object behind a path in a local tree
+struct LocalEntry {
+    oid: gix::ObjectId,
+    is_dir: bool,
+}
+
+impl LocalSource {
+    /// Open a repository and index its tree at `rev` (defaults to `HEAD`)
+    pub fn open(path: &Path, rev: Option<&str>) -> Result<Self> {
+        let repository = gix::ThreadSafeRepository::open(path)
+            .with_context(|| format!("Failed to open git repository at {}", path.display()))?;
+        let local = repository.to_thread_local();
+
+        let rev = rev.unwrap_or("HEAD");
+        let commit = local
+            .rev_parse_single(rev)
+            .with_context(|| format!("Failed to resolve revision {}", rev))?
+            .object()?
+            .peel_to_commit()
+            .with_context(|| format!("Revision {} is not a commit", rev))?;
+        let tree = commit.tree()?;
+
+        let entries = tree
+            .traverse()
+            .breadthfirst
+            .files()?
+            .into_iter()
+            .map(|entry| {
+                let local = LocalEntry {
+                    oid: entry.oid,
+                    is_dir: entry.mode.is_tree(),
+                };
+                (entry.filepath.to_string(), local)
+            })
+            .collect();
+
+        // Name the repository after its directory, as a clone would be
+        let root = local.workdir().unwrap_or_else(|| local.git_dir());
+        let repo = root
+            .canonicalize()
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "repository".to_string());
+
+        Ok(Self {
+            repository,
+            owner: "local".to_string(),
+            repo: repo.trim_end_matches(".git").to_string(),
+            commit: commit.id,
+            entries,
+        })
+    }
+}
+
+#[async_trait]
+impl RepoSource for LocalSource {
+    fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    fn repo(&self) -> &str {
+        &self.repo
+    }
+
+    async fn commit_sha(&self) -> Result<String> {
+        Ok(self.commit.to_string())
+    }
+
+    async fn file_exists(&self, path: &str) -> Result<bool> {
+        Ok(self.entries.contains_key(path.trim_end_matches('/')))
+    }
+
+    async fn read_file(&self, path: &str) -> Result<String> {
+        let entry = self
+            .entries
+            .get(path)
+            .filter(|entry| !entry.is_dir)
+            .with_context(|| format!("File not found: {}", path))?;
+        let repository = self.repository.to_thread_local();
+        let blob = repository.find_blob(entry.oid)?;
+
+        Ok(String::from_utf8_lossy(&blob.data).into_owned())
+    }
+
+    async fn license(&self) -> Result<Option<LicenseInfo>> {
+        for candidate in LICENSE_FILES {
+            if let Ok(text) = self.read_file(candidate).await {
+                return Ok(Some(detect_license(candidate, &text)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn list_tree(&self) -> Result<Vec<TreeEntry>> {
+        Ok(self
+            .entries
+            .iter()
+            .map(|(path, entry)| TreeEntry {
+                path: path.clone(),
+                is_dir: entry.is_dir,
+            })
+            .collect())
+    }
+}
+
+/// File names probed for license text, in order of preference
+const LICENSE_FILES: &[&str] = &[
+    "LICENSE.txt",
+    "LICENSE",
+    "LICENSE.md",
+    "LICENSE.adoc",
+    "COPYING",
+];
+
+/// Identify the license in the file at `path`, preferring an SPDX identifier line
+fn detect_license(path: &str, text: &str) -> LicenseInfo {
+    if let Some(spdx) = text
+        .lines()
+        .find_map(|line| line.split("SPDX-License-Identifier:").nth(1))
+        .map(str::trim)
+    {
+        let key = spdx
+            .to_lowercase()
+            .trim_end_matches("-or-later")
+            .trim_end_matches("-only")
+            .to_string();
+        return LicenseInfo {
+            key,
+            name: spdx.to_string(),
+            path: path.to_string(),
+        };
+    }
+
+    // Fall back to the distinctive headings of common license texts
+    let known = [
+        ("GNU AFFERO GENERAL PUBLIC LICENSE", "agpl-3.0", "GNU Affero General Public License v3.0"),
+        ("GNU LESSER GENERAL PUBLIC LICENSE", "lgpl-3.0", "GNU Lesser General Public License v3.0"),
+        ("GNU GENERAL PUBLIC LICENSE", "gpl-3.0", "GNU General Public License v3.0"),
+        ("Mozilla Public License Version 2.0", "mpl-2.0", "Mozilla Public License 2.0"),
+        ("Apache License", "apache-2.0", "Apache License 2.0"),
+        ("Permission is hereby granted, free of charge", "mit", "MIT License"),
+    ];
+
+    known
+        .iter()
+        .find(|(marker, _, _)| text.contains(marker))
+        .map(|(_, key, name)| LicenseInfo {
+            key: key.to_string(),
+            name: name.to_string(),
+            path: path.to_string(),
+        })
+        .unwrap_or_else(|| LicenseInfo {
+            key: "other".to_string(),
+            name: "Other".to_string(),
+            path: path.to_string(),
+        })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Repository held in memory, at commit `abc1234` of `owner/repo`
+    #[derive(Default)]
+    pub struct MemorySource {
+        /// Files by path, with their content
+        pub files: BTreeMap<String, String>,
+        pub license: Option<LicenseInfo>,
+        /// Fail to detect the license, as when the API is unreachable
+        pub license_unavailable: bool,
+        pub org_config: Option<String>,
+    }
+
+    impl MemorySource {
+        /// Repository made of `files`, as `(path, content)` pairs
+        pub fn with_files(files: &[(&str, &str)]) -> Self {
+            Self {
+                files: files
+                    .iter()
+                    .map(|(path, content)| (path.to_string(), content.to_string()))
+                    .collect(),
+                ..Self::default()
+            }
+        }
+
+        /// The files and every directory above them
+        fn tree(&self) -> TreeIndex {
+            let mut entries = Vec::new();
+            for path in self.files.keys() {
+                let mut dirs = path.match_indices('/').map(|(end, _)| &path[..end]);
+                entries.extend(dirs.by_ref().map(|dir| TreeEntry {
+                    path: dir.to_string(),
+                    is_dir: true,
+                }));
+                entries.push(TreeEntry {
+                    path: path.clone(),
+                    is_dir: false,
+                });
+            }
+            entries.into_iter().collect()
+        }
+    }
+
+    #[async_trait]
+    impl RepoSource for MemorySource {
+        fn owner(&self) -> &str {
+            "owner"
+        }
+
+        fn repo(&self) -> &str {
+            "repo"
+        }
+
+        async fn commit_sha(&self) -> Result<String> {
+            Ok("abc1234".to_string())
+        }
+
+        async fn file_exists(&self, path: &str) -> Result<bool> {
+            Ok(self.tree().contains(path))
+        }
+
+        async fn read_file(&self, path: &str) -> Result<String> {
+            self.files
+                .get(path)
+                .cloned()
+                .with_context(|| format!("{} not found", path))
+        }
+
+        async fn license(&self) -> Result<Option<LicenseInfo>> {
+            if self.license_unavailable {
+                anyhow::bail!("Failed to get license: 503 Service Unavailable");
+            }
+            Ok(self.license.clone())
+        }
+
+        async fn list_tree(&self) -> Result<Vec<TreeEntry>> {
+            Ok(self.tree().entries())
+        }
+
+        async fn org_config(&self) -> Option<String> {
+            self.org_config.clone()
+        }
+    }
+
+    #[test]
+    fn licenses_are_detected_from_spdx_lines_or_headings() {
+        let cases = [
+            (
+                "// SPDX-License-Identifier: MPL-2.0\n",
+                "mpl-2.0",
+                "MPL-2.0",
+            ),
+            (
+                "SPDX-License-Identifier: GPL-3.0-or-later",
+                "gpl-3.0",
+                "GPL-3.0-or-later",
+            ),
+            (
+                "                 Apache License\n  Version 2.0",
+                "apache-2.0",
+                "Apache License 2.0",
+            ),
+            (
+                "Permission is hereby granted, free of charge, ...",
+                "mit",
+                "MIT License",
+            ),
+            ("All rights reserved.", "other", "Other"),
+        ];
+
+        for (text, key, name) in cases {
+            let license = detect_license("LICENSE", text);
+            assert_eq!(
+                (license.key.as_str(), license.name.as_str()),
+                (key, name),
+                "{}",
+                text
+            );
+            assert_eq!(license.path, "LICENSE");
+        }
+    }
+
+    #[test]
+    fn full_tree_cache_drops_the_least_recently_used() {
+        let now = Instant::now();
+        let cases = [
+            (vec![("a", 1), ("b", 2)], 3, vec!["a", "b"]),
+            (vec![("a", 1), ("b", 2), ("c", 3)], 3, vec!["b", "c"]),
+            (vec![("a", 3), ("b", 1), ("c", 2)], 3, vec!["a", "c"]),
+            (vec![("a", 3), ("b", 1), ("c", 2)], 2, vec!["a"]),
+        ];
+
+        for (entries, max, kept) in cases {
+            let mut cache: HashMap<String, CachedTree> = entries
+                .iter()
+                .map(|(key, secs)| {
+                    let cached = CachedTree {
+                        index: Arc::new(TreeIndex::default()),
+                        used: now + std::time::Duration::from_secs(*secs),
+                    };
+                    (key.to_string(), cached)
+                })
+                .collect();
+            make_room(&mut cache, max);
+
+            let mut keys: Vec<&str> = cache.keys().map(String::as_str).collect();
+            keys.sort();
+            assert_eq!(keys, kept, "{:?} within {}", entries, max);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+// SPDX-License-Identifier: PMPL-1.0-or-later
+
+//! Templates for user-facing text
+//!
+//! Check-run output, issue and pull request bodies and scaffolded files are
+//! rendered from minijinja templates that get the live report and the policy
+//! pack tables as context. The templates shipped in `templates/` are embedded
+//! in the binary; a template directory given at startup overrides any of them
+//! by file name.
+
+use anyhow::{Context, Result};
+use minijinja::{Environment, Value, context};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, RwLock};
+use tracing::info;
+
+use crate::policy::{self, PackDocument};
+use crate::rsr::{
+    BUILTIN_PACKS, CheckCategory, CheckStatus, ComplianceReport, PolicyPack, ReportDiff,
+    report_conclusion,
+};
+
+/// Templates shipped with rhodibot, by name
+///
+/// A template named `report.md` is overridden by `report.md.j2` in the
+/// template directory.
+const EMBEDDED_TEMPLATES: &[(&str, &str)] = &[
+    ("report.md", include_str!("../templates/report.md.j2")),
+    ("report-document.md", include_str!("../templates/report-document.md.j2")),
+    ("report.html", include_str!("../templates/report.html.j2")),
+    ("report.adoc", include_str!("../templates/report.adoc.j2")),
+    ("report.junit.xml", include_str!("../templates/report.junit.xml.j2")),
+    ("diff.md", include_str!("../templates/diff.md.j2")),
+    ("checklist.md", include_str!("../templates/checklist.md.j2")),
+    ("pr-comment.md", include_str!("../templates/pr-comment.md.j2")),
+    ("scaffold-pr.md", include_str!("../templates/scaffold-pr.md.j2")),
+    ("scaffold/README.adoc", include_str!("../templates/scaffold/README.adoc.j2")),
+    ("scaffold/SECURITY.md", include_str!("../templates/scaffold/SECURITY.md.j2")),
+    ("scaffold/CONTRIBUTING.md", include_str!("../templates/scaffold/CONTRIBUTING.md.j2")),
+    ("scaffold/CODE_OF_CONDUCT.md", include_str!("../templates/scaffold/CODE_OF_CONDUCT.md.j2")),
+    ("scaffold/CLAUDE.md", include_str!("../templates/scaffold/CLAUDE.md.j2")),
+    ("scaffold/STATE.scm", include_str!("../templates/scaffold/STATE.scm.j2")),
+    ("scaffold/META.scm", include_str!("../templates/scaffold/META.scm.j2")),
+    ("scaffold/ECOSYSTEM.scm", include_str!("../templates/scaffold/ECOSYSTEM.scm.j2")),
+];
+
+/// Report categories in display order
+const CATEGORIES: [CheckCategory; 5] = [
+    CheckCategory::Documentation,
+    CheckCategory::Security,
+    CheckCategory::Governance,
+    CheckCategory::Structure,
+    CheckCategory::LanguagePolicy,
+];
+
+/// Templates in effect, swapped as a whole on load
+static ENVIRONMENT: LazyLock<RwLock<Arc<Environment<'static>>>> = LazyLock::new(|| {
+    let env = environment(None)
+        .unwrap_or_else(|e| panic!("embedded templates are invalid: {:#}", e));
+    RwLock::new(Arc::new(env))
+});
+
+/// A report category as seen by templates
+#[derive(Debug, Serialize)]
+struct Category {
+    /// Value of `check.category` for checks in this category
+    id: CheckCategory,
+    title: String,
+}
+
+/// Build an environment from the embedded templates and any overrides
+fn environment(dir: Option<&Path>) -> Result<Environment<'static>> {
+    let mut env = Environment::new();
+    env.set_keep_trailing_newline(true);
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+
+    for (name, embedded) in EMBEDDED_TEMPLATES {
+        let path = dir.map(|dir| dir.join(format!("{}.j2", name)));
+
+        let source = match path {
+            Some(path) if path.is_file() => {
+                info!("Using template override {}", path.display());
+                std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?
+            }
+            _ => embedded.to_string(),
+        };
+
+        env.add_template_owned(name.to_string(), source)
+            .with_context(|| format!("Invalid template {}", name))?;
+    }
+
+    Ok(env)
+}
+
+/// Load templates, overridden from a directory if given, and make them current
+pub fn load(dir: Option<&Path>) -> Result<()> {
+    let Some(dir) = dir else {
+        return Ok(());
+    };
+
+    if !dir.is_dir() {
+        anyhow::bail!("Template directory {} does not exist", dir.display());
+    }
+
+    *ENVIRONMENT.write().expect("templates poisoned") = Arc::new(environment(Some(dir))?);
+
+    Ok(())
+}
+
+/// Render a template by name
+pub fn render<S: Serialize>(name: &str, ctx: S) -> Result<String> {
+    let env = ENVIRONMENT.read().expect("templates poisoned").clone();
+    let text = env
+        .get_template(name)?
+        .render(ctx)
+        .with_context(|| format!("Failed to render template {}", name))?;
+
+    Ok(text)
+}
+
+/// Context shared by every template: the policy pack tables and categories
+fn base_context() -> Value {
+    let packs = policy::current();
+    let packs: BTreeMap<String, &PackDocument> = BUILTIN_PACKS
+        .iter()
+        .map(|pack| (pack.to_string(), packs.get(*pack)))
+        .collect();
+    let categories: Vec<Category> = CATEGORIES
+        .iter()
+        .map(|id| Category {
+            id: *id,
+            title: id.to_string(),
+        })
+        .collect();
+
+    context! { packs, categories }
+}
+
+/// Check-run text for a report
+pub fn report_text(report: &ComplianceReport) -> Result<String> {
+    render(
+        "report.md",
+        context! {
+            report,
+            policy_summary => crate::rsr::policy_summary(report.policy),
+            ..base_context()
+        },
+    )
+}
+
+/// A report as a standalone document: `report-document.md`, `report.html`,
+/// `report.adoc` or `report.junit.xml`
+pub fn report_document(name: &str, report: &ComplianceReport) -> Result<String> {
+    let count = |status| report.checks.iter().filter(|check| check.status == status).count();
+
+    render(
+        name,
+        context! {
+            report,
+            policy_summary => crate::rsr::policy_summary(report.policy),
+            conclusion => report_conclusion(report),
+            failures => count(CheckStatus::Fail),
+            skipped => count(CheckStatus::Skip),
+            ..base_context()
+        },
+    )
+}
+
+/// Check-run text for the difference between a PR's base and head
+pub fn diff_text(diff: &ReportDiff) -> Result<String> {
+    render(
+        "diff.md",
+        context! {
+            diff,
+            regressed => diff.regressed(),
+            score_lowered => diff.score_lowered(),
+            ..base_context()
+        },
+    )
+}
+
+/// Body of the sticky pull request comment
+///
+/// `diff` compares the head with the base branch and `since_push` with the
+/// previous head of the pull request.
+pub fn pr_comment(
+    report: &ComplianceReport,
+    diff: Option<&ReportDiff>,
+    since_push: Option<&ReportDiff>,
+    conclusion: &str,
+) -> Result<String> {
+    render(
+        "pr-comment.md",
+        context! {
+            report,
+            policy_summary => crate::rsr::policy_summary(report.policy),
+            diff,
+            regressed => diff.is_some_and(ReportDiff::regressed),
+            score_lowered => diff.is_some_and(ReportDiff::score_lowered),
+            since_push,
+            conclusion,
+            ..base_context()
+        },
+    )
+}
+
+/// Body of the RSR checklist issue
+///
+/// Lists the checks of `report` when there is one, otherwise the tables of
+/// the pack the repository will be checked against.
+pub fn checklist(
+    owner: &str,
+    repo: &str,
+    policy: PolicyPack,
+    pack: &PackDocument,
+    report: Option<&ComplianceReport>,
+) -> Result<String> {
+    render(
+        "checklist.md",
+        context! {
+            owner,
+            repo,
+            policy,
+            pack,
+            report,
+            ..base_context()
+        },
+    )
+}
@@ -3,17 +3,37 @@
 //! Webhook handling module
 
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use hmac::{Hmac, Mac};
 use serde::Deserialize;
+use serde::de::DeserializeOwned;
 use sha2::Sha256;
+use std::collections::HashMap;
 use tracing::{info, warn};
 
+use crate::auth;
+use crate::badge;
+use crate::checklist;
+use crate::comment;
 use crate::config::Config;
-use crate::github::{CreateCheckRun, CheckRunOutput, GitHubClient};
+use crate::history;
+use crate::metrics;
+use crate::github::{
+    CheckAnnotation, CheckRunAction, CheckRunOutput, CreateCheckRun, GitHubClient, UpdateCheckRun,
+};
 use crate::rsr;
+use crate::scaffold;
+use crate::source::GitHubSource;
+use crate::templates;
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Name of check runs, completed with the policy once it is known
+const CHECK_RUN_NAME: &str = "RSR Compliance";
+
+/// Identifier of the check-run button that opens the scaffold pull request
+const OPEN_FIX_PR_ACTION: &str = "open_fix_pr";
+
 /// Verify GitHub webhook signature
 pub fn verify_signature(secret: &str, payload: &str, signature: &str) -> bool {
     let signature = signature.strip_prefix("sha256=").unwrap_or(signature);
@@ -31,9 +51,127 @@ pub fn verify_signature(secret: &str, payload: &str, signature: &str) -> bool {
     mac.verify_slice(&signature_bytes).is_ok()
 }
 
+/// What the attempts of a webhook job share
+///
+/// A failed job is retried from the start, so the check runs an earlier
+/// attempt created are remembered and completed by the next attempt instead
+/// of being created again.
+#[derive(Debug, Default)]
+pub struct JobState {
+    /// Check runs created so far, by head commit
+    check_runs: HashMap<String, u64>,
+    /// Whether a failure is final rather than followed by a retry
+    pub last_attempt: bool,
+}
+
+/// A payload that does not have the shape of its event
+///
+/// Retrying cannot help, so such jobs are given up at once.
+#[derive(Debug)]
+pub struct InvalidPayload(serde_json::Error);
+
+impl std::fmt::Display for InvalidPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid payload: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidPayload {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+fn parse<T: DeserializeOwned>(body: &str) -> Result<T, InvalidPayload> {
+    serde_json::from_str(body).map_err(InvalidPayload)
+}
+
+/// Dispatch a webhook delivery to the handler for its event type
+pub async fn handle_event(
+    config: &Config,
+    state: &mut JobState,
+    event_type: &str,
+    body: &str,
+) -> Result<()> {
+    match event_type {
+        "push" => handle_push(config, state, body).await,
+        "pull_request" => handle_pull_request(config, state, body).await,
+        "check_suite" => handle_check_suite(config, state, body).await,
+        "check_run" => handle_check_run(config, state, body).await,
+        "repository" => handle_repository(config, body).await,
+        "installation" | "installation_repositories" => handle_installation(config, body).await,
+        _ => {
+            info!("Ignoring event type: {}", event_type);
+            Ok(())
+        }
+    }
+}
+
+/// Key under which deliveries doing the same work are coalesced
+///
+/// Deliveries that check a commit of a repo share a key. Others, including
+/// actions the handlers ignore, have none and never coalesce, so they cannot
+/// replace a waiting delivery that does work.
+pub fn job_key(event_type: &str, payload: &serde_json::Value) -> Option<String> {
+    let action = payload["action"].as_str().unwrap_or_default();
+    let sha_pointer = match (event_type, action) {
+        ("push", _) => {
+            let default_branch = payload.pointer("/repository/default_branch")?.as_str()?;
+            if payload["ref"] != format!("refs/heads/{}", default_branch) {
+                return None;
+            }
+            "/after"
+        }
+        ("pull_request", "opened" | "reopened" | "synchronize") => "/pull_request/head/sha",
+        ("check_suite", "requested") => {
+            let pulls = payload.pointer("/check_suite/pull_requests")?.as_array()?;
+            if new_suite_checked_elsewhere(
+                payload.pointer("/check_suite/head_branch")?.as_str(),
+                payload.pointer("/repository/default_branch")?.as_str()?,
+                !pulls.is_empty(),
+            ) {
+                return None;
+            }
+            "/check_suite/head_sha"
+        }
+        ("check_suite", "rerequested") => "/check_suite/head_sha",
+        ("check_run", "rerequested") => "/check_run/head_sha",
+        _ => return None,
+    };
+
+    let owner = payload.pointer("/repository/owner/login")?.as_str()?;
+    let repo = payload.pointer("/repository/name")?.as_str()?;
+    let sha = payload.pointer(sha_pointer)?.as_str()?;
+
+    Some(format!("{}:{}/{}@{}", event_type, owner, repo, sha))
+}
+
+/// When the change a delivery reports happened, if the payload says
+///
+/// Only fields set by the event itself count. Other timestamps, such as when
+/// an installation or repository was last updated, can be far older than the
+/// event. Re-run requests are excluded: they legitimately refer to old suites.
+pub fn event_timestamp(event_type: &str, payload: &serde_json::Value) -> Option<DateTime<Utc>> {
+    let action = payload.get("action").and_then(serde_json::Value::as_str);
+    let pointer = match (event_type, action) {
+        ("push", _) => "/repository/pushed_at",
+        ("pull_request", _) => "/pull_request/updated_at",
+        ("repository", Some("created")) => "/repository/created_at",
+        _ => return None,
+    };
+
+    match payload.pointer(pointer)? {
+        serde_json::Value::Number(seconds) => DateTime::from_timestamp(seconds.as_i64()?, 0),
+        serde_json::Value::String(time) => DateTime::parse_from_rfc3339(time)
+            .ok()
+            .map(|time| time.with_timezone(&Utc)),
+        _ => None,
+    }
+}
+
 /// Handle push event
-pub async fn handle_push(config: &Config, body: &str) -> Result<()> {
-    let event: PushEvent = serde_json::from_str(body)?;
+pub async fn handle_push(config: &Config, state: &mut JobState, body: &str) -> Result<()> {
+    let event: PushEvent = parse(body)?;
 
     info!(
         "Push to {}/{} on branch {}",
@@ -47,54 +185,54 @@ pub async fn handle_push(config: &Config, body: &str) -> Result<()> {
         return Ok(());
     }
 
-    // Run compliance check
-    let client = GitHubClient::new(config);
-    let report = rsr::check_compliance(
-        config,
-        &event.repository.owner.login,
-        &event.repository.name,
+    // Run compliance check on the pushed commit, as the installation that sent the event
+    let client = GitHubClient::for_installation(config, event.installation.map(|i| i.id)).await?;
+    let owner = &event.repository.owner.login;
+    let name = &event.repository.name;
+
+    let CheckOutcome {
+        repo_config,
+        report,
+        ..
+    } = run_check(
+        &client,
+        state,
+        owner,
+        name,
+        &event.repository.default_branch,
+        &event.after,
+        None,
     )
     .await?;
 
-    // Create check run - fail if required checks didn't pass
-    let conclusion = if !report.required_passed {
-        "failure"
-    } else if report.percentage >= 70.0 {
-        "success"
-    } else if report.percentage >= 50.0 {
-        "neutral"
-    } else {
-        "failure"
-    };
+    info!("Completed check run for push (policy: {})", report.policy);
 
-    let check_run = CreateCheckRun {
-        name: format!("RSR Compliance ({})", report.policy),
-        head_sha: event.after,
-        status: "completed".to_string(),
-        conclusion: Some(conclusion.to_string()),
-        output: Some(CheckRunOutput {
-            title: format!("RSR Score: {:.0}% ({})", report.percentage, report.policy),
-            summary: report.summary.clone(),
-            text: Some(format_report_text(&report)),
-        }),
-    };
+    // Badges and the compliance gauge show the default branch
+    if !event.repository.private {
+        badge::update(&report, config.badge_ttl);
+    }
+    metrics::compliance(&report);
 
-    client
-        .create_check_run(
-            &event.repository.owner.login,
-            &event.repository.name,
-            &check_run,
-        )
-        .await?;
+    // Keep the checklist issue in line with the default branch
+    let synced = match auth::bot_login(config).await {
+        Ok(bot) => checklist::sync(&client, owner, name, bot, &repo_config, Some(&report)).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = synced {
+        warn!("Failed to update RSR checklist issue: {}", e);
+    }
 
-    info!("Created check run for push (policy: {})", report.policy);
+    // Propose the missing files the bot can scaffold
+    if repo_config.autofix_enabled() {
+        open_fix_pr(&client, owner, name, &report, false).await;
+    }
 
     Ok(())
 }
 
 /// Handle pull request event
-pub async fn handle_pull_request(config: &Config, body: &str) -> Result<()> {
-    let event: PullRequestEvent = serde_json::from_str(body)?;
+pub async fn handle_pull_request(config: &Config, state: &mut JobState, body: &str) -> Result<()> {
+    let event: PullRequestEvent = parse(body)?;
 
     info!(
         "Pull request #{} {} on {}/{}",
@@ -104,111 +242,439 @@ pub async fn handle_pull_request(config: &Config, body: &str) -> Result<()> {
         event.repository.name
     );
 
-    // Only check on opened/synchronized
-    if event.action != "opened" && event.action != "synchronize" {
+    // Only check on opened/reopened/synchronized
+    if !matches!(event.action.as_str(), "opened" | "reopened" | "synchronize") {
         return Ok(());
     }
 
-    // Run compliance check
-    let client = GitHubClient::new(config);
-    let report = rsr::check_compliance(
-        config,
-        &event.repository.owner.login,
-        &event.repository.name,
+    // Run compliance checks on the PR base and head, as the installation that sent the event
+    let client = GitHubClient::for_installation(config, event.installation.map(|i| i.id)).await?;
+    let owner = &event.repository.owner.login;
+    let name = &event.repository.name;
+    let head_sha = &event.pull_request.head.sha;
+
+    let CheckOutcome { report, diff, .. } = run_check(
+        &client,
+        state,
+        owner,
+        name,
+        &event.pull_request.head.r#ref,
+        head_sha,
+        Some(&event.pull_request.base.sha),
+    )
+    .await?;
+
+    info!("Completed check run for PR (policy: {})", report.policy);
+
+    // Compare with the previous head to show what the push changed
+    let since_push = match event.before.as_deref() {
+        Some(before) if event.action == "synchronize" && before != head_sha => {
+            diff_since(&client, owner, name, before, &report).await
+        }
+        _ => None,
+    };
+
+    let conclusion = pull_request_conclusion(&report, diff.as_ref());
+    let body = templates::pr_comment(&report, diff.as_ref(), since_push.as_ref(), conclusion)?;
+
+    let number = event.pull_request.number;
+    let upserted = match auth::bot_login(config).await {
+        Ok(bot) => comment::upsert(&client, owner, name, number, bot, &body).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = upserted {
+        warn!("Failed to update PR comment: {}", e);
+    }
+
+    Ok(())
+}
+
+/// Compare a report with that of an earlier commit
+///
+/// The earlier report is taken from the history when it has one, so the
+/// commit is only checked again without it.
+async fn diff_since(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    sha: &str,
+    report: &rsr::ComplianceReport,
+) -> Option<rsr::ReportDiff> {
+    if let Some(history) = history::current() {
+        match history.commit_reports(owner, repo, sha).await {
+            Ok(reports) => {
+                if let Some(previous) = reports.first() {
+                    return Some(rsr::ReportDiff::from_base(
+                        previous.percentage,
+                        &previous.base_checks(),
+                        report,
+                    ));
+                }
+            }
+            Err(e) => warn!("Failed to look up the report of {} in history: {:#}", sha, e),
+        }
+    }
+
+    let source = GitHubSource::new(client, owner, repo, Some(sha));
+    match rsr::check_compliance(&source).await {
+        Ok(previous) => Some(rsr::ReportDiff::between(&previous, report)),
+        Err(e) => {
+            warn!("Failed to check previous PR head {}: {}", sha, e);
+            None
+        }
+    }
+}
+
+/// Whether the push or pull request handler already checks a new suite's head
+///
+/// They check pushes to the default branch and the heads of pull requests.
+fn new_suite_checked_elsewhere(
+    head_branch: Option<&str>,
+    default_branch: &str,
+    has_pull_requests: bool,
+) -> bool {
+    has_pull_requests || head_branch == Some(default_branch)
+}
+
+/// Handle check suite event
+///
+/// Checks new suites (`requested`) and re-requested ones (the "Re-run all
+/// checks" button). New suites on the default branch or of a pull request are
+/// left to the push and pull request handlers, so they are not checked twice.
+pub async fn handle_check_suite(config: &Config, state: &mut JobState, body: &str) -> Result<()> {
+    let event: CheckSuiteEvent = parse(body)?;
+
+    info!(
+        "Check suite {} for {} on {}/{}",
+        event.action,
+        event.check_suite.head_sha,
+        event.repository.owner.login,
+        event.repository.name
+    );
+
+    match event.action.as_str() {
+        "rerequested" => {}
+        "requested"
+            if !new_suite_checked_elsewhere(
+                event.check_suite.head_branch.as_deref(),
+                &event.repository.default_branch,
+                !event.check_suite.pull_requests.is_empty(),
+            ) => {}
+        _ => return Ok(()),
+    }
+
+    let client = GitHubClient::for_installation(config, event.installation.map(|i| i.id)).await?;
+    rerun(&client, state, &event.repository, &event.check_suite).await
+}
+
+/// Handle check run event
+///
+/// Re-runs a single check run, or performs the action of one of its buttons.
+pub async fn handle_check_run(config: &Config, state: &mut JobState, body: &str) -> Result<()> {
+    let event: CheckRunEvent = parse(body)?;
+
+    info!(
+        "Check run {} {} on {}/{}",
+        event.check_run.id,
+        event.action,
+        event.repository.owner.login,
+        event.repository.name
+    );
+
+    let client = GitHubClient::for_installation(config, event.installation.map(|i| i.id)).await?;
+    let owner = &event.repository.owner.login;
+    let name = &event.repository.name;
+
+    match event.action.as_str() {
+        "rerequested" => {
+            rerun(
+                &client,
+                state,
+                &event.repository,
+                &event.check_run.check_suite,
+            )
+            .await
+        }
+        "requested_action" => {
+            let identifier = event
+                .requested_action
+                .map(|action| action.identifier)
+                .unwrap_or_default();
+
+            if identifier != OPEN_FIX_PR_ACTION {
+                info!("Ignoring unknown check run action {:?}", identifier);
+                return Ok(());
+            }
+
+            let source = GitHubSource::new(&client, owner, name, Some(&event.check_run.head_sha));
+            let report = rsr::check_compliance(&source).await?;
+            open_fix_pr(&client, owner, name, &report, true).await;
+
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Check the head of a check suite, gating on its pull request if it has one
+async fn rerun(
+    client: &GitHubClient,
+    state: &mut JobState,
+    repository: &Repository,
+    suite: &CheckSuite,
+) -> Result<()> {
+    let base_sha = suite
+        .pull_requests
+        .first()
+        .map(|pull| pull.base.sha.as_str());
+
+    let CheckOutcome { report, .. } = run_check(
+        client,
+        state,
+        &repository.owner.login,
+        &repository.name,
+        suite.head_branch.as_deref().unwrap_or_default(),
+        &suite.head_sha,
+        base_sha,
     )
     .await?;
 
-    // Create check run - fail if required checks didn't pass
-    let conclusion = if !report.required_passed {
-        "failure"
-    } else if report.percentage >= 70.0 {
-        "success"
-    } else if report.percentage >= 50.0 {
-        "neutral"
-    } else {
-        "failure"
+    info!(
+        "Completed check run for check suite (policy: {})",
+        report.policy
+    );
+
+    Ok(())
+}
+
+/// What a check run was completed from
+struct CheckOutcome {
+    repo_config: rsr::RepoConfig,
+    report: rsr::ComplianceReport,
+    /// Difference from the base commit, if one was checked
+    diff: Option<rsr::ReportDiff>,
+}
+
+/// Whether a check run is rhodibot's, named with or without its policy
+fn is_rsr_check_run(name: &str) -> bool {
+    name.strip_prefix(CHECK_RUN_NAME)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(" ("))
+}
+
+/// Check a commit under a check run
+///
+/// The check run is created `in_progress` before anything is evaluated and
+/// completed once the report is ready. A commit that already has one, e.g.
+/// from a check suite checked before its pull request was opened, has that
+/// check run restarted instead, so it never shows two. If the report cannot
+/// be produced, the check run stays in progress for the job's next attempt
+/// and is only failed after the last one. With a base commit the check run gates on the
+/// difference from the base. The report is recorded in the history under the
+/// branch it was checked for.
+async fn run_check(
+    client: &GitHubClient,
+    state: &mut JobState,
+    owner: &str,
+    repo: &str,
+    branch: &str,
+    head_sha: &str,
+    base_sha: Option<&str>,
+) -> Result<CheckOutcome> {
+    let started = CreateCheckRun {
+        name: CHECK_RUN_NAME.to_string(),
+        head_sha: head_sha.to_string(),
+        status: "in_progress".to_string(),
+        conclusion: None,
+        output: Some(CheckRunOutput {
+            title: "Checking RSR compliance".to_string(),
+            summary: "Rhodibot is evaluating this commit.".to_string(),
+            text: None,
+            annotations: Vec::new(),
+        }),
+        actions: Vec::new(),
+    };
+    let check_run_id = match state.check_runs.get(head_sha) {
+        Some(id) => *id,
+        None => {
+            let existing = client
+                .list_check_runs(owner, repo, head_sha)
+                .await?
+                .into_iter()
+                .find(|run| is_rsr_check_run(&run.name));
+            let id = match existing {
+                Some(run) => {
+                    let restarted = UpdateCheckRun {
+                        status: Some(started.status.clone()),
+                        output: started.output.clone(),
+                        actions: Some(Vec::new()),
+                        ..Default::default()
+                    };
+                    client
+                        .update_check_run(owner, repo, run.id, &restarted)
+                        .await?;
+                    run.id
+                }
+                None => client.create_check_run(owner, repo, &started).await?.id,
+            };
+            state.check_runs.insert(head_sha.to_string(), id);
+            id
+        }
+    };
+
+    let (outcome, completed) =
+        match evaluate(client, owner, repo, head_sha, base_sha).await {
+            Ok(evaluated) => evaluated,
+            Err(e) if !state.last_attempt => return Err(e),
+            Err(e) => {
+                let failed = UpdateCheckRun {
+                    status: Some("completed".to_string()),
+                    conclusion: Some("failure".to_string()),
+                    output: Some(CheckRunOutput {
+                        title: "RSR check could not run".to_string(),
+                        summary: format!("{:#}", e),
+                        text: None,
+                        annotations: Vec::new(),
+                    }),
+                    ..Default::default()
+                };
+                if let Err(e) = client
+                    .update_check_run(owner, repo, check_run_id, &failed)
+                    .await
+                {
+                    warn!("Failed to mark check run {} as failed: {}", check_run_id, e);
+                }
+                return Err(e);
+            }
+        };
+
+    client
+        .update_check_run(owner, repo, check_run_id, &completed)
+        .await?;
+
+    if let Some(history) = history::current() {
+        let branch = (!branch.is_empty()).then_some(branch);
+        if let Err(e) = history.record(&outcome.report, branch).await {
+            warn!("Failed to record report history: {:#}", e);
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// Evaluate a commit and build the update completing its check run
+async fn evaluate(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    head_sha: &str,
+    base_sha: Option<&str>,
+) -> Result<(CheckOutcome, UpdateCheckRun)> {
+    let head_source = GitHubSource::new(client, owner, repo, Some(head_sha));
+    let repo_config = rsr::load_repo_config(&head_source).await;
+    let report = rsr::check_compliance_with_policy(&head_source, &repo_config).await?;
+
+    let diff = match base_sha {
+        Some(base_sha) => {
+            let base_source = GitHubSource::new(client, owner, repo, Some(base_sha));
+            match rsr::check_compliance(&base_source).await {
+                Ok(base_report) => Some(rsr::ReportDiff::between(&base_report, &report)),
+                Err(e) => {
+                    warn!("Failed to check PR base, gating on head only: {}", e);
+                    None
+                }
+            }
+        }
+        None => None,
     };
 
-    let check_run = CreateCheckRun {
-        name: format!("RSR Compliance ({})", report.policy),
-        head_sha: event.pull_request.head.sha,
-        status: "completed".to_string(),
+    let conclusion = pull_request_conclusion(&report, diff.as_ref());
+
+    let mut text = String::new();
+    if let Some(ref diff) = diff {
+        text.push_str(&templates::diff_text(diff)?);
+    }
+    text.push_str(&templates::report_text(&report)?);
+
+    // Offer to scaffold what is missing, also where autofix is off
+    let mut actions = Vec::new();
+    if !scaffold::missing_files(&report).is_empty() {
+        actions.push(CheckRunAction {
+            label: "Open fix PR".to_string(),
+            description: "Scaffold the missing RSR files".to_string(),
+            identifier: OPEN_FIX_PR_ACTION.to_string(),
+        });
+    }
+
+    let completed = UpdateCheckRun {
+        name: Some(format!("{} ({})", CHECK_RUN_NAME, report.policy)),
+        status: Some("completed".to_string()),
         conclusion: Some(conclusion.to_string()),
         output: Some(CheckRunOutput {
             title: format!("RSR Score: {:.0}% ({})", report.percentage, report.policy),
             summary: report.summary.clone(),
-            text: Some(format_report_text(&report)),
+            text: Some(text),
+            annotations: annotations(&report, diff.as_ref()),
         }),
+        actions: Some(actions),
     };
 
-    client
-        .create_check_run(
-            &event.repository.owner.login,
-            &event.repository.name,
-            &check_run,
-        )
-        .await?;
+    let outcome = CheckOutcome {
+        repo_config,
+        report,
+        diff,
+    };
 
-    info!("Created check run for PR (policy: {})", report.policy);
+    Ok((outcome, completed))
+}
 
-    Ok(())
+/// Open or refresh the scaffold pull request, logging the outcome
+///
+/// `requested` is set when someone asked for it with the check run's button.
+async fn open_fix_pr(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    report: &rsr::ComplianceReport,
+    requested: bool,
+) {
+    match scaffold::open_or_update_pr(client, owner, repo, report, requested).await {
+        Ok(Some(pull)) => info!("Scaffold pull request: {}", pull.html_url),
+        Ok(None) => info!("No scaffold pull request for {}/{}", owner, repo),
+        Err(e) => warn!("Failed to open scaffold pull request: {}", e),
+    }
 }
 
 /// Handle repository event
 pub async fn handle_repository(config: &Config, body: &str) -> Result<()> {
-    let event: RepositoryEvent = serde_json::from_str(body)?;
+    let event: RepositoryEvent = parse(body)?;
 
     info!(
         "Repository {} {}/{}",
         event.action, event.repository.owner.login, event.repository.name
     );
 
-    // On repository creation, create an issue with RSR checklist
+    // On repository creation, open the RSR checklist issue
     if event.action == "created" {
-        let client = GitHubClient::new(config);
-
-        let issue_body = r#"## RSR Compliance Checklist
-
-Welcome to the hyperpolymath organization! Please ensure your repository follows the Rhodium Standard Repository guidelines.
-
-### Required Files
-- [ ] `README.adoc` - Project documentation (AsciiDoc format)
-- [ ] `LICENSE.txt` - License file (AGPL-3.0, MIT, Apache-2.0, or MPL-2.0)
-- [ ] `SECURITY.md` - Security policy
-- [ ] `CONTRIBUTING.md` - Contribution guidelines
-- [ ] `CODE_OF_CONDUCT.md` - Code of conduct
-- [ ] `.claude/CLAUDE.md` - AI assistant instructions
-- [ ] `STATE.scm` - Project state (Guile Scheme)
-- [ ] `META.scm` - Meta information (Guile Scheme)
-- [ ] `ECOSYSTEM.scm` - Ecosystem position (Guile Scheme)
-
-### Language Policy (CCCP)
-**Allowed:** ReScript, Rust, Deno, Gleam, Bash, Julia, Ada, OCaml
-**Banned:** TypeScript, Node.js, npm, Go, Python (except SaltStack)
-
-### CI/CD
-- [ ] GitHub Actions workflows in `.github/workflows/`
-- [ ] SHA-pinned actions
-- [ ] `permissions: read-all` on workflows
-
-For more details, see the [RSR Documentation](https://github.com/hyperpolymath/rhodium-standard-repositories).
-
----
-*This issue was created automatically by Rhodibot*
-"#;
-
-        match client
-            .create_issue(
-                &event.repository.owner.login,
-                &event.repository.name,
-                "[Rhodibot] RSR Compliance Checklist",
-                issue_body,
-                &["documentation", "rsr-compliance"],
-            )
+        let client =
+            GitHubClient::for_installation(config, event.installation.map(|i| i.id)).await?;
+
+        let owner = &event.repository.owner.login;
+        let name = &event.repository.name;
+
+        // List the checks of the initial commit, or the pack tables of an empty repo
+        let source = GitHubSource::new(&client, owner, name, None);
+        let repo_config = rsr::load_repo_config(&source).await;
+        let report = rsr::check_compliance_with_policy(&source, &repo_config)
             .await
-        {
-            Ok(issue) => info!("Created RSR checklist issue: {}", issue.html_url),
-            Err(e) => warn!("Failed to create issue: {}", e),
+            .ok();
+        let synced = match auth::bot_login(config).await {
+            Ok(bot) => {
+                checklist::sync(&client, owner, name, bot, &repo_config, report.as_ref()).await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = synced {
+            warn!("Failed to create RSR checklist issue: {}", e);
         }
     }
 
@@ -217,10 +683,11 @@ pub async fn handle_repository(config: &Config, body: &str) -> Result<()> {
 
 /// Handle installation event
 pub async fn handle_installation(_config: &Config, body: &str) -> Result<()> {
-    let event: InstallationEvent = serde_json::from_str(body)?;
+    let event: InstallationEvent = parse(body)?;
 
     info!(
-        "Installation {} for {}",
+        "Installation {} {} for {}",
+        event.installation.id,
         event.action,
         event.installation.account.login
     );
@@ -228,72 +695,58 @@ pub async fn handle_installation(_config: &Config, body: &str) -> Result<()> {
     Ok(())
 }
 
-/// Format report as markdown text
-fn format_report_text(report: &rsr::ComplianceReport) -> String {
-    let mut text = String::new();
-
-    // Policy info header
-    text.push_str(&format!("## Policy: {}\n\n", report.policy));
-    text.push_str(&format!("{}\n\n", rsr::policy_summary(report.policy)));
-
-    if !report.required_passed {
-        text.push_str("> :x: **Required checks failed** - repository does not meet minimum RSR requirements\n\n");
+/// Check-run conclusion, gated on the difference from the base if known
+///
+/// Fails only on regressions; existing debt on the base is informational.
+pub fn pull_request_conclusion(
+    report: &rsr::ComplianceReport,
+    diff: Option<&rsr::ReportDiff>,
+) -> &'static str {
+    match diff {
+        Some(diff) if diff.regressed() => "failure",
+        Some(_) if rsr::report_conclusion(report) == "success" => "success",
+        Some(_) => "neutral",
+        None => rsr::report_conclusion(report),
     }
+}
 
-    text.push_str("## Detailed Results\n\n");
-
-    let categories = [
-        ("Documentation", rsr::CheckCategory::Documentation),
-        ("Security", rsr::CheckCategory::Security),
-        ("Governance", rsr::CheckCategory::Governance),
-        ("Structure", rsr::CheckCategory::Structure),
-        ("Language Policy", rsr::CheckCategory::LanguagePolicy),
-    ];
-
-    for (cat_name, cat) in categories {
-        let cat_checks: Vec<_> = report
-            .checks
-            .iter()
-            .filter(|c| c.category == cat)
-            .collect();
-
-        if cat_checks.is_empty() {
-            continue;
-        }
-
-        text.push_str(&format!("### {}\n\n", cat_name));
-
-        for check in cat_checks {
-            let icon = match check.status {
-                rsr::CheckStatus::Pass => ":white_check_mark:",
-                rsr::CheckStatus::Fail => ":x:",
-                rsr::CheckStatus::Warn => ":warning:",
-                rsr::CheckStatus::Skip => ":fast_forward:",
-            };
-
-            let severity_badge = match check.severity {
-                rsr::Severity::Required => "[required]",
-                rsr::Severity::Recommended => "[recommended]",
-                rsr::Severity::Optional => "[optional]",
+/// Annotations on the files flagged by failing checks, e.g. banned files or a
+/// non-standard license
+///
+/// Each carries the check's message, on the flagged line or else the first.
+/// The level follows the check's severity. With a diff, failures that already
+/// exist on the base branch are only annotated as notices.
+pub fn annotations(
+    report: &rsr::ComplianceReport,
+    diff: Option<&rsr::ReportDiff>,
+) -> Vec<CheckAnnotation> {
+    report
+        .checks
+        .iter()
+        .filter(|check| matches!(check.status, rsr::CheckStatus::Fail | rsr::CheckStatus::Warn))
+        .flat_map(|check| {
+            let existing = diff.is_some_and(|diff| diff.existing_failures.contains(&check.name));
+            let level = match check.severity {
+                _ if existing => "notice",
+                rsr::Severity::Required => "failure",
+                rsr::Severity::Recommended => "warning",
+                rsr::Severity::Optional => "notice",
             };
 
-            if check.max_points > 0 {
-                text.push_str(&format!(
-                    "- {} **{}** {}: {} ({}/{})\n",
-                    icon, check.name, severity_badge, check.message, check.points, check.max_points
-                ));
-            } else {
-                text.push_str(&format!(
-                    "- {} **{}** {}: {}\n",
-                    icon, check.name, severity_badge, check.message
-                ));
-            }
-        }
-
-        text.push('\n');
-    }
-
-    text
+            check.locations.iter().map(move |location| {
+                let line = location.line.unwrap_or(1);
+
+                CheckAnnotation {
+                    path: location.path.clone(),
+                    start_line: line,
+                    end_line: line,
+                    annotation_level: level.to_string(),
+                    message: check.message.clone(),
+                    title: Some(format!("{} [{}]", check.name, check.severity)),
+                }
+            })
+        })
+        .collect()
 }
 
 // Event types
@@ -303,30 +756,80 @@ struct PushEvent {
     r#ref: String,
     after: String,
     repository: Repository,
+    installation: Option<InstallationRef>,
 }
 
 #[derive(Debug, Deserialize)]
 struct PullRequestEvent {
     action: String,
+    /// Previous head commit, on `synchronize`
+    before: Option<String>,
     pull_request: PullRequest,
     repository: Repository,
+    installation: Option<InstallationRef>,
 }
 
 #[derive(Debug, Deserialize)]
 struct PullRequest {
     number: u64,
-    head: PullRequestHead,
+    head: PullRequestRef,
+    base: PullRequestRef,
 }
 
+/// Head or base commit of a pull request
 #[derive(Debug, Deserialize)]
-struct PullRequestHead {
+struct PullRequestRef {
+    /// Branch name
+    #[serde(default)]
+    r#ref: String,
     sha: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct CheckSuiteEvent {
+    action: String,
+    check_suite: CheckSuite,
+    repository: Repository,
+    installation: Option<InstallationRef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckSuite {
+    /// Branch the suite ran on, absent for suites on tags or deleted branches
+    head_branch: Option<String>,
+    head_sha: String,
+    /// Open pull requests the suite's head belongs to
+    #[serde(default)]
+    pull_requests: Vec<PullRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckRunEvent {
+    action: String,
+    check_run: CheckRunPayload,
+    /// The button that was clicked, for `requested_action`
+    requested_action: Option<RequestedAction>,
+    repository: Repository,
+    installation: Option<InstallationRef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckRunPayload {
+    id: u64,
+    head_sha: String,
+    check_suite: CheckSuite,
+}
+
+#[derive(Debug, Deserialize)]
+struct RequestedAction {
+    identifier: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct RepositoryEvent {
     action: String,
     repository: Repository,
+    installation: Option<InstallationRef>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -337,9 +840,16 @@ struct InstallationEvent {
 
 #[derive(Debug, Deserialize)]
 struct Installation {
+    id: u64,
     account: Account,
 }
 
+/// Installation reference carried by events delivered to a GitHub App
+#[derive(Debug, Deserialize)]
+struct InstallationRef {
+    id: u64,
+}
+
 #[derive(Debug, Deserialize)]
 struct Account {
     login: String,
@@ -349,6 +859,7 @@ struct Account {
 struct Repository {
     name: String,
     default_branch: String,
+    private: bool,
     owner: Owner,
 }
 
@@ -356,3 +867,223 @@ struct Repository {
 struct Owner {
     login: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsr::tests::{check, report};
+    use crate::rsr::{CheckStatus, Location, Severity};
+    use serde_json::json;
+
+    fn payload(action: Option<&str>, git_ref: &str) -> serde_json::Value {
+        let mut payload = json!({
+            "ref": git_ref,
+            "after": "abc123",
+            "pull_request": { "head": { "sha": "abc123" } },
+            "check_suite": { "head_sha": "abc123", "head_branch": "feature", "pull_requests": [] },
+            "check_run": { "head_sha": "abc123" },
+            "repository": {
+                "name": "repo",
+                "private": false,
+                "default_branch": "main",
+                "owner": { "login": "owner" }
+            }
+        });
+        if let Some(action) = action {
+            payload["action"] = json!(action);
+        }
+        payload
+    }
+
+    #[test]
+    fn job_key_only_for_actionable_deliveries() {
+        let cases = [
+            (
+                "push",
+                None,
+                "refs/heads/main",
+                Some("push:owner/repo@abc123"),
+            ),
+            ("push", None, "refs/heads/feature", None),
+            ("push", None, "refs/tags/v1.0.0", None),
+            (
+                "pull_request",
+                Some("opened"),
+                "",
+                Some("pull_request:owner/repo@abc123"),
+            ),
+            (
+                "pull_request",
+                Some("reopened"),
+                "",
+                Some("pull_request:owner/repo@abc123"),
+            ),
+            (
+                "pull_request",
+                Some("synchronize"),
+                "",
+                Some("pull_request:owner/repo@abc123"),
+            ),
+            ("pull_request", Some("closed"), "", None),
+            ("pull_request", Some("labeled"), "", None),
+            (
+                "check_suite",
+                Some("requested"),
+                "",
+                Some("check_suite:owner/repo@abc123"),
+            ),
+            (
+                "check_suite",
+                Some("rerequested"),
+                "",
+                Some("check_suite:owner/repo@abc123"),
+            ),
+            ("check_suite", Some("completed"), "", None),
+            (
+                "check_run",
+                Some("rerequested"),
+                "",
+                Some("check_run:owner/repo@abc123"),
+            ),
+            ("check_run", Some("requested_action"), "", None),
+            ("check_run", Some("completed"), "", None),
+            ("repository", Some("created"), "", None),
+            ("installation", Some("created"), "", None),
+        ];
+
+        for (event, action, git_ref, expected) in cases {
+            assert_eq!(
+                job_key(event, &payload(action, git_ref)).as_deref(),
+                expected,
+                "{} {:?} {}",
+                event,
+                action,
+                git_ref
+            );
+        }
+    }
+
+    #[test]
+    fn job_key_skips_new_suites_checked_elsewhere() {
+        let mut on_default_branch = payload(Some("requested"), "");
+        on_default_branch["check_suite"]["head_branch"] = json!("main");
+        assert_eq!(job_key("check_suite", &on_default_branch), None);
+
+        let mut of_pull_request = payload(Some("requested"), "");
+        of_pull_request["check_suite"]["pull_requests"] = json!([{ "number": 7 }]);
+        assert_eq!(job_key("check_suite", &of_pull_request), None);
+
+        // Re-runs are always checked
+        of_pull_request["action"] = json!("rerequested");
+        assert!(job_key("check_suite", &of_pull_request).is_some());
+    }
+
+    #[test]
+    fn job_key_needs_repo_and_commit() {
+        let mut payload = payload(Some("opened"), "");
+        payload["pull_request"]["head"] = json!({});
+        assert_eq!(job_key("pull_request", &payload), None);
+    }
+
+    #[test]
+    fn event_timestamps_are_times_of_the_event() {
+        let time = "2026-10-16T12:00:00Z";
+        let at = DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Utc);
+        let payload = |action: &str| {
+            serde_json::json!({
+                "action": action,
+                "repository": {
+                    "pushed_at": 1_792_152_000,
+                    "created_at": time,
+                    "updated_at": time,
+                },
+                "pull_request": { "updated_at": time },
+                "installation": { "updated_at": time },
+            })
+        };
+
+        let cases = [
+            (
+                "push",
+                "",
+                Some(DateTime::from_timestamp(1_792_152_000, 0).unwrap()),
+            ),
+            ("pull_request", "synchronize", Some(at)),
+            ("repository", "created", Some(at)),
+            ("repository", "renamed", None),
+            ("repository", "archived", None),
+            ("installation", "created", None),
+            ("installation_repositories", "added", None),
+            ("check_suite", "rerequested", None),
+        ];
+
+        for (event_type, action, expected) in cases {
+            assert_eq!(
+                event_timestamp(event_type, &payload(action)),
+                expected,
+                "{} {}",
+                event_type,
+                action
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_payloads_are_recognized() {
+        let e: anyhow::Error = parse::<PushEvent>("{}").unwrap_err().into();
+        assert!(e.is::<InvalidPayload>());
+    }
+
+    #[test]
+    fn annotations_follow_severity_and_existing_debt() {
+        let mut banned = check("no-*.ts", Severity::Required, CheckStatus::Fail);
+        banned.locations = vec![
+            Location::file("src/app.ts"),
+            Location {
+                path: "src/lib.ts".to_string(),
+                line: Some(3),
+            },
+        ];
+        let mut license = check("license-type", Severity::Recommended, CheckStatus::Warn);
+        license.locations = vec![Location::file("LICENSE")];
+        let mut passed = check("README.adoc", Severity::Required, CheckStatus::Pass);
+        passed.locations = vec![Location::file("README.adoc")];
+        let report = report(vec![banned, license, passed]);
+
+        let levels = |diff| {
+            annotations(&report, diff)
+                .into_iter()
+                .map(|a| (a.path, a.start_line, a.annotation_level))
+                .collect::<Vec<_>>()
+        };
+        let expected = |banned: &str| {
+            vec![
+                ("src/app.ts".to_string(), 1, banned.to_string()),
+                ("src/lib.ts".to_string(), 3, banned.to_string()),
+                ("LICENSE".to_string(), 1, "warning".to_string()),
+            ]
+        };
+
+        assert_eq!(levels(None), expected("failure"));
+
+        let diff = rsr::ReportDiff {
+            new_failures: Vec::new(),
+            removed_required: Vec::new(),
+            existing_failures: vec!["no-*.ts".to_string()],
+            fixed: Vec::new(),
+            base_percentage: 50.0,
+            head_percentage: 50.0,
+        };
+        assert_eq!(levels(Some(&diff)), expected("notice"));
+    }
+
+    #[test]
+    fn check_runs_are_recognized_with_or_without_their_policy() {
+        assert!(is_rsr_check_run("RSR Compliance"));
+        assert!(is_rsr_check_run("RSR Compliance (strict)"));
+        assert!(!is_rsr_check_run("RSR Compliance Extra"));
+        assert!(!is_rsr_check_run("CI"));
+    }
+}